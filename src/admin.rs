@@ -0,0 +1,178 @@
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::{Context, Result, anyhow, bail};
+use serde::Deserialize;
+use serde::de::DeserializeOwned;
+use zenoh::bytes::ZBytes;
+
+/// The admin space of every router, with its sessions and plugins.
+pub const ROUTERS: &str = "@/*/router";
+
+/// The link state graphs of every router, for both the routers and the peers networks.
+pub const LINKSTATE: &str = "@/*/router/linkstate/*";
+
+/// The transports of the local session with other nodes, and their links.
+pub fn transports(zid: &str) -> String {
+    format!("@/{zid}/session/transport/unicast/**")
+}
+
+/// Parses a JSON admin reply, e.g. a [`Router`].
+pub fn parse<T: DeserializeOwned>(keyexpr: &str, payload: &ZBytes) -> Result<T> {
+    serde_json::from_slice(&payload.to_bytes())
+        .with_context(|| format!("invalid admin reply on {keyexpr}"))
+}
+
+/// What a router reports about itself on `@/<zid>/router`.
+#[derive(Deserialize)]
+pub struct Router {
+    pub zid: String,
+    /// The Zenoh version followed by the compiler it's built with.
+    pub version: Option<String>,
+    #[serde(default)]
+    pub locators: Vec<String>,
+    #[serde(default)]
+    pub sessions: Vec<Session>,
+    /// The started plugins by ID, `null` if the router doesn't support plugins.
+    #[serde(default)]
+    pub plugins: Option<BTreeMap<String, Plugin>>,
+}
+
+impl Router {
+    /// The Zenoh version without the compiler details.
+    pub fn short_version(&self) -> Option<&str> {
+        self.version.as_deref()?.split_whitespace().next()
+    }
+}
+
+/// A transport session of a router with another node.
+#[derive(Deserialize)]
+pub struct Session {
+    pub peer: String,
+    pub whatami: String,
+    #[serde(default)]
+    pub links: Vec<Link>,
+}
+
+#[derive(Deserialize)]
+pub struct Link {
+    pub src: String,
+    pub dst: String,
+}
+
+/// A transport of the local session, on `@/<zid>/session/transport/unicast/<zid>`.
+#[derive(Deserialize)]
+pub struct Transport {
+    pub zid: String,
+    pub whatami: String,
+}
+
+/// The protocol of a locator, e.g. `tcp` for `tcp/127.0.0.1:7447`.
+pub fn protocol(locator: &str) -> &str {
+    locator
+        .split_once('/')
+        .map_or(locator, |(protocol, _)| protocol)
+}
+
+#[derive(Deserialize)]
+pub struct Plugin {
+    pub name: String,
+    pub path: Option<String>,
+}
+
+/// A link state graph of a router, e.g. from `@/<zid>/router/linkstate/routers`.
+pub struct Graph {
+    /// The router that reported the graph.
+    pub zid: String,
+    /// The network the graph is about, `routers` or `peers`.
+    pub network: String,
+    pub nodes: Vec<String>,
+    pub edges: Vec<Edge>,
+}
+
+pub struct Edge {
+    pub from: String,
+    pub to: String,
+    /// Routers add up to 1% to the configured weights to break ties, so it's rounded to two
+    /// decimals, which are enough to compare them.
+    pub weight: Option<f64>,
+}
+
+impl Graph {
+    /// Parses the Graphviz DOT the routers report their link state in, e.g.
+    ///
+    /// ```text
+    /// graph {
+    ///     0 [ label = "aa" ]
+    ///     1 [ label = "bb" ]
+    ///     1 -- 0 [ label = "100.0" ]
+    /// }
+    /// ```
+    pub fn parse(keyexpr: &str, payload: &ZBytes) -> Result<Self> {
+        let invalid = || format!("invalid link state on {keyexpr}");
+        let (zid, network) = match keyexpr.split('/').collect::<Vec<_>>()[..] {
+            ["@", zid, _, "linkstate", network] => (zid.to_string(), network.to_string()),
+            _ => bail!("unexpected link state key: {keyexpr}"),
+        };
+        let dot = payload.try_to_string().with_context(invalid)?;
+
+        let mut labels = HashMap::new();
+        let mut nodes = Vec::new();
+        let mut edges = Vec::new();
+        for line in dot.lines().map(str::trim) {
+            if line.is_empty() || line == "}" || line.starts_with("graph") {
+                continue;
+            }
+            let (ids, label) = match line.split_once('[') {
+                Some((ids, attributes)) => (ids.trim(), label(attributes)),
+                None => (line, None),
+            };
+            match ids.split_once("--") {
+                Some((from, to)) => edges.push((
+                    from.trim().to_string(),
+                    to.trim().to_string(),
+                    label
+                        .and_then(|weight| weight.parse::<f64>().ok())
+                        .map(|weight| (weight * 100.0).round() / 100.0),
+                )),
+                None => {
+                    let zid = label.unwrap_or(ids).to_string();
+                    labels.insert(ids.to_string(), zid.clone());
+                    nodes.push(zid);
+                }
+            }
+        }
+
+        let node = |id: &str| {
+            labels
+                .get(id)
+                .cloned()
+                .ok_or_else(|| anyhow!("unknown node: {id}"))
+                .with_context(invalid)
+        };
+        let edges = edges
+            .into_iter()
+            .map(|(from, to, weight)| {
+                Ok(Edge {
+                    from: node(&from)?,
+                    to: node(&to)?,
+                    weight,
+                })
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Graph {
+            zid,
+            network,
+            nodes,
+            edges,
+        })
+    }
+}
+
+/// The value of the `label` attribute of a DOT node or edge.
+fn label(attributes: &str) -> Option<&str> {
+    let (_, rest) = attributes.split_once("label")?;
+    let (_, rest) = rest.split_once('"')?;
+    let (label, _) = rest.split_once('"')?;
+    Some(label)
+}
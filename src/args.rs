@@ -0,0 +1,284 @@
+use std::collections::VecDeque;
+use std::ops::Range;
+use std::str::FromStr;
+
+use anyhow::{Error, Result, anyhow, bail};
+
+/// Arguments of a single command, e.g. `get --render hex demo/**`.
+///
+/// Flags and options are taken out first, and the remaining positional arguments are consumed in order.
+/// Everything after `--` is treated as a positional argument.
+pub struct Args {
+    args: VecDeque<Arg>,
+    literal: usize,
+    /// The line arguments are parsed from, `None` if they are already split by the shell.
+    line: Option<String>,
+}
+
+struct Arg {
+    value: String,
+    /// Where the argument is written in the line, including its quotes.
+    span: Range<usize>,
+    index: usize,
+}
+
+impl Args {
+    /// Creates arguments that are already split, e.g. by the shell invoking the CLI.
+    pub fn new<I>(args: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let args = args
+            .into_iter()
+            .enumerate()
+            .map(|(index, value)| Arg {
+                value: value.into(),
+                span: 0..0,
+                index,
+            })
+            .collect();
+        Args::with_line(args, None)
+    }
+
+    /// Splits the line into arguments like a shell does.
+    ///
+    /// Arguments are separated by whitespace, unless it's quoted with `'` or `"` or escaped with `\`.
+    /// Inside double quotes `\` escapes `"` and `\`, and `\n` and `\t` are a newline and a tab.
+    pub fn parse(line: &str) -> Result<Self> {
+        let mut args = VecDeque::new();
+        let mut chars = line.char_indices().peekable();
+        loop {
+            while chars.next_if(|(_, c)| c.is_whitespace()).is_some() {}
+            let Some(&(start, _)) = chars.peek() else {
+                break;
+            };
+
+            let mut value = String::new();
+            let mut end = line.len();
+            while let Some((i, c)) = chars.next() {
+                match c {
+                    c if c.is_whitespace() => {
+                        end = i;
+                        break;
+                    }
+                    '\'' => loop {
+                        match chars.next() {
+                            Some((_, '\'')) => break,
+                            Some((_, c)) => value.push(c),
+                            None => bail!("unterminated quote, missing closing '"),
+                        }
+                    },
+                    '"' => loop {
+                        match chars.next() {
+                            Some((_, '"')) => break,
+                            Some((_, '\\')) => match chars.next() {
+                                Some((_, 'n')) => value.push('\n'),
+                                Some((_, 't')) => value.push('\t'),
+                                Some((_, c @ ('"' | '\\'))) => value.push(c),
+                                Some((_, c)) => {
+                                    value.push('\\');
+                                    value.push(c);
+                                }
+                                None => bail!("unterminated quote, missing closing \""),
+                            },
+                            Some((_, c)) => value.push(c),
+                            None => bail!("unterminated quote, missing closing \""),
+                        }
+                    },
+                    '\\' => match chars.next() {
+                        Some((_, c)) => value.push(c),
+                        None => bail!("unterminated escape at the end of the line"),
+                    },
+                    c => value.push(c),
+                }
+            }
+
+            let index = args.len();
+            args.push_back(Arg {
+                value,
+                span: start..end,
+                index,
+            });
+        }
+
+        Ok(Args::with_line(args, Some(line.to_string())))
+    }
+
+    fn with_line(mut args: VecDeque<Arg>, line: Option<String>) -> Self {
+        let literal = match args.iter().position(|arg| arg.value == "--") {
+            Some(pos) => {
+                args.remove(pos);
+                args.len() - pos
+            }
+            None => 0,
+        };
+        Args {
+            args,
+            literal,
+            line,
+        }
+    }
+
+    /// Whether the arguments are typed in interactive mode rather than given to the CLI.
+    pub fn is_interactive(&self) -> bool {
+        self.line.is_some()
+    }
+
+    /// Takes out a switch like `--table`, returning whether it was given.
+    pub fn flag(&mut self, names: &[&str]) -> bool {
+        let mut found = false;
+        while let Some(pos) = self.position(|arg| names.contains(&arg)) {
+            self.args.remove(pos);
+            found = true;
+        }
+        found
+    }
+
+    /// Takes out an option with a value like `--render hex` or `--render=hex`, which can only be
+    /// given once.
+    pub fn option(&mut self, names: &[&str]) -> Result<Option<String>> {
+        let mut value = None;
+        while let Some(pos) = self.position(|arg| matches(names, arg)) {
+            if value.is_some() {
+                bail!("{} given more than once", names[0]);
+            }
+            let arg = self.args.remove(pos).expect("position must be valid").value;
+            match arg.split_once('=') {
+                Some((_, inline)) if arg.starts_with("--") => {
+                    value = Some(inline.to_string());
+                }
+                _ => {
+                    if pos >= self.options_end() {
+                        bail!("missing value for {arg}");
+                    }
+                    value = self.args.remove(pos).map(|arg| arg.value);
+                }
+            }
+        }
+        Ok(value)
+    }
+
+    /// Takes out an option and parses its value.
+    pub fn parsed<T>(&mut self, names: &[&str]) -> Result<Option<T>>
+    where
+        T: FromStr,
+        T::Err: Into<Error>,
+    {
+        self.parsed_with(names, |value| value.parse().map_err(Into::into))
+    }
+
+    /// Takes out an option and parses its value with the given function, e.g. for foreign types.
+    pub fn parsed_with<T>(
+        &mut self,
+        names: &[&str],
+        parse: impl FnOnce(&str) -> Result<T>,
+    ) -> Result<Option<T>> {
+        self.option(names)?
+            .map(|value| {
+                parse(&value).map_err(|err| anyhow!("invalid value for {}: {}", names[0], err))
+            })
+            .transpose()
+    }
+
+    /// The next argument, without taking it out, e.g. to dispatch on the name of a command.
+    pub fn peek(&self) -> Option<&str> {
+        self.args.front().map(|arg| arg.value.as_str())
+    }
+
+    /// The remaining arguments with where they're written in the line, and whether they're
+    /// options, i.e. they start with `-` and aren't after `--`. Used to look at a line as it's
+    /// typed without taking anything out.
+    pub fn spans(&self) -> impl Iterator<Item = (Range<usize>, &str, bool)> {
+        self.args.iter().enumerate().map(|(pos, arg)| {
+            let option = pos < self.options_end() && is_option(&arg.value);
+            (arg.span.clone(), arg.value.as_str(), option)
+        })
+    }
+
+    /// Takes out the next positional argument.
+    pub fn next(&mut self) -> Result<Option<String>> {
+        if self.options_end() > 0 && is_option(&self.args[0].value) {
+            bail!("unknown option: {}", self.args[0].value);
+        }
+        if self.args.len() == self.literal {
+            self.literal = self.literal.saturating_sub(1);
+        }
+        Ok(self.args.pop_front().map(|arg| arg.value))
+    }
+
+    /// Takes out all the remaining positional arguments.
+    pub fn rest(&mut self) -> Result<Vec<String>> {
+        let mut rest = Vec::new();
+        while let Some(arg) = self.next()? {
+            rest.push(arg);
+        }
+        Ok(rest)
+    }
+
+    /// Takes out the remaining positional arguments as a single one, e.g. a payload with spaces.
+    ///
+    /// The arguments are unquoted and unescaped, and joined with the whitespace written between
+    /// them in the line, so `put demo/a "hello"  world` puts `hello  world`. Arguments split by
+    /// the shell are joined with a space.
+    pub fn rest_of_line(&mut self) -> Result<Option<String>> {
+        if let Some(pos) = self.position(is_option) {
+            bail!("unknown option: {}", self.args[pos].value);
+        }
+        if self.args.is_empty() {
+            return Ok(None);
+        }
+
+        let args = std::mem::take(&mut self.args);
+        self.literal = 0;
+
+        let mut rest = String::new();
+        let mut prev: Option<&Arg> = None;
+        for arg in &args {
+            if let Some(prev) = prev {
+                match &self.line {
+                    Some(line) if prev.index + 1 == arg.index => {
+                        rest.push_str(&line[prev.span.end..arg.span.start]);
+                    }
+                    _ => rest.push(' '),
+                }
+            }
+            rest.push_str(&arg.value);
+            prev = Some(arg);
+        }
+        Ok(Some(rest))
+    }
+
+    /// Makes sure all the arguments are consumed.
+    pub fn finish(mut self) -> Result<()> {
+        if let Some(arg) = self.next()? {
+            bail!("unexpected argument: {arg}");
+        }
+        Ok(())
+    }
+
+    fn options_end(&self) -> usize {
+        self.args.len() - self.literal
+    }
+
+    fn position(&self, pred: impl Fn(&str) -> bool) -> Option<usize> {
+        self.args
+            .iter()
+            .take(self.options_end())
+            .position(|arg| pred(&arg.value))
+    }
+}
+
+fn matches(names: &[&str], arg: &str) -> bool {
+    names.iter().any(|name| {
+        arg == *name
+            || (name.starts_with("--")
+                && arg
+                    .strip_prefix(name)
+                    .is_some_and(|rest| rest.starts_with('=')))
+    })
+}
+
+fn is_option(arg: &str) -> bool {
+    arg.len() > 1 && arg.starts_with('-') && !arg[1..].starts_with(|c: char| c.is_ascii_digit())
+}
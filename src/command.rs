@@ -1,166 +1,1156 @@
-use std::time::Duration;
+use std::collections::HashMap;
+use std::future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
 
 use anyhow::{Result, anyhow};
 use tokio::sync::mpsc;
-use tokio::time;
-use zenoh::Session;
+use tokio::task::{self, JoinSet};
+use tokio::time::{self, MissedTickBehavior};
 use zenoh::bytes::{Encoding, ZBytes};
+use zenoh::config::WhatAmIMatcher;
+use zenoh::key_expr::KeyExpr;
+use zenoh::qos::{CongestionControl, Priority, Reliability};
+use zenoh::query::{ConsolidationMode, QueryTarget};
+use zenoh::sample::SampleKind;
+use zenoh::scouting::Hello;
+use zenoh::time::Timestamp;
+use zenoh::{Config, Session, Wait};
 
-pub type KeyValue = (String, ZBytes);
+use super::admin;
+use super::keys::Keys;
+use super::payload;
+use super::topology::Topology;
+
+/// A sample received as a reply to a query or from a subscription.
+pub struct Sample {
+    pub keyexpr: String,
+    pub kind: SampleKind,
+    pub payload: ZBytes,
+    pub encoding: Encoding,
+    pub timestamp: Option<Timestamp>,
+    pub priority: Priority,
+    pub congestion_control: CongestionControl,
+    pub express: bool,
+    pub attachment: Option<ZBytes>,
+}
+
+impl From<zenoh::sample::Sample> for Sample {
+    fn from(sample: zenoh::sample::Sample) -> Self {
+        Sample {
+            keyexpr: sample.key_expr().to_string(),
+            kind: sample.kind(),
+            payload: sample.payload().to_owned(),
+            encoding: sample.encoding().to_owned(),
+            timestamp: sample.timestamp().cloned(),
+            priority: sample.priority(),
+            congestion_control: sample.congestion_control(),
+            express: sample.express(),
+            attachment: sample.attachment().cloned(),
+        }
+    }
+}
+
+/// A reply to a query, either a sample or an error reported by the queryable.
+pub struct Reply {
+    pub result: Result<Sample, ReplyError>,
+    /// The ID of the Zenoh instance that replied, if it's known.
+    pub replier: Option<String>,
+}
+
+/// An error reply, its payload and encoding are up to the queryable.
+pub struct ReplyError {
+    pub payload: ZBytes,
+    pub encoding: Encoding,
+}
+
+/// A liveliness token appearing (`Put`) or disappearing (`Delete`).
+pub type TokenChange = (String, SampleKind);
+
+/// A query received by a queryable.
+pub struct Query {
+    pub keyexpr: String,
+    pub parameters: String,
+    pub payload: Option<(ZBytes, Encoding)>,
+}
+
+/// How a queryable replies to the queries it receives.
+pub enum Replies {
+    /// Replies to every query with the same payload.
+    Payload(String),
+    /// Replies with the value of every key that intersects with the query.
+    Table(Vec<(String, String)>),
+}
+
+/// Options of a query besides its selector, the defaults are the ones of Zenoh.
+#[derive(Default)]
+pub struct GetOptions {
+    pub target: QueryTarget,
+    pub consolidation: ConsolidationMode,
+    pub timeout: Option<Duration>,
+    pub payload: Option<(ZBytes, Encoding)>,
+    pub attachment: Option<ZBytes>,
+}
+
+/// Options of a put or a delete, the defaults are the ones of Zenoh.
+#[derive(Default)]
+pub struct PublishOptions {
+    pub priority: Priority,
+    pub congestion_control: CongestionControl,
+    pub express: bool,
+    pub reliability: Reliability,
+    pub attachment: Option<ZBytes>,
+    /// Whether to timestamp the sample with the clock of the session.
+    pub timestamp: bool,
+}
+
+/// How often and how many times `pub` publishes.
+#[derive(Default)]
+pub struct Schedule {
+    /// The time between two publications, as fast as possible if `None`.
+    pub period: Option<Duration>,
+    /// Whether to start over once the lines are exhausted.
+    pub repeat: bool,
+    /// Stops after publishing this many samples, once the lines are exhausted otherwise.
+    pub count: Option<u64>,
+}
+
+/// A side of a benchmark.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BenchRole {
+    Publisher,
+    Subscriber,
+}
+
+/// The throughput measured by one side of `bench throughput`.
+pub struct Throughput {
+    pub role: BenchRole,
+    pub samples: u64,
+    pub bytes: u64,
+    pub elapsed: Duration,
+    /// Samples missing from the received sequence, `None` for the publisher.
+    pub dropped: Option<u64>,
+}
+
+/// A round trip of `ping`, `rtt` is `None` if no pong was received in time.
+pub struct Pong {
+    pub seq: u64,
+    pub size: usize,
+    pub rtt: Option<Duration>,
+}
+
+/// A Zenoh node the session knows about, e.g. for `peers` or `nodes`.
+pub struct Node {
+    pub zid: String,
+    pub whatami: String,
+    /// The locators the node listens on, only known for routers with an admin space.
+    pub locators: Vec<String>,
+    /// The protocols of the links with the node, or with the router it's connected to.
+    pub protocols: Vec<String>,
+    /// How long the session has been connected to the node, only known for direct connections.
+    pub age: Option<Duration>,
+    pub connection: Connection,
+}
+
+/// How the session reaches a node.
+pub enum Connection {
+    /// The session itself.
+    Local,
+    Direct,
+    /// Through a session of the given router.
+    Via(String),
+}
 
 pub enum Command {
     Get {
         selector: String,
-        reply: mpsc::Sender<Result<KeyValue>>,
+        options: GetOptions,
+        reply: mpsc::Sender<Result<Reply>>,
     },
     Put {
         keyexpr: String,
-        payload: String,
+        payload: ZBytes,
+        encoding: Encoding,
+        options: PublishOptions,
         reply: mpsc::Sender<Result<()>>,
     },
     Delete {
         keyexpr: String,
+        options: PublishOptions,
         reply: mpsc::Sender<Result<()>>,
     },
+    Publish {
+        keyexpr: String,
+        payloads: payload::Payloads,
+        encoding: Encoding,
+        options: PublishOptions,
+        schedule: Schedule,
+        /// Receives the number of published samples once done.
+        reply: mpsc::Sender<Result<u64>>,
+    },
     Subscribe {
         keyexpr: String,
-        reply: mpsc::Sender<Result<KeyValue>>,
+        reply: mpsc::Sender<Result<Sample>>,
+    },
+    /// Runs a publisher, a subscriber or both in the session, and reports their throughput.
+    BenchThroughput {
+        keyexpr: String,
+        publish: bool,
+        subscribe: bool,
+        size: usize,
+        duration: Duration,
+        reply: mpsc::Sender<Result<Throughput>>,
+    },
+    /// Publishes pings on `<keyexpr>/ping` and waits for them on `<keyexpr>/pong`.
+    Ping {
+        keyexpr: String,
+        count: Option<u64>,
+        size: usize,
+        interval: Duration,
+        timeout: Duration,
+        reply: mpsc::Sender<Result<Pong>>,
+    },
+    /// Echoes the samples of `<keyexpr>/ping` on `<keyexpr>/pong`.
+    Pong {
+        keyexpr: String,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    Queryable {
+        keyexpr: String,
+        replies: Replies,
+        reply: mpsc::Sender<Result<Query>>,
+    },
+    LivelinessToken {
+        keyexpr: String,
+        reply: mpsc::Sender<Result<()>>,
+    },
+    LivelinessGet {
+        keyexpr: String,
+        reply: mpsc::Sender<Result<String>>,
+    },
+    LivelinessSubscribe {
+        keyexpr: String,
+        history: bool,
+        reply: mpsc::Sender<Result<TokenChange>>,
     },
     Zid {
         reply: mpsc::Sender<Result<String>>,
     },
     Peers {
-        reply: mpsc::Sender<Result<String>>,
+        reply: mpsc::Sender<Result<Node>>,
     },
     Routers {
-        reply: mpsc::Sender<Result<String>>,
+        reply: mpsc::Sender<Result<Node>>,
+    },
+    /// Lists the session, the nodes it's connected to and the ones the routers are connected to.
+    Nodes {
+        reply: mpsc::Sender<Result<Node>>,
+    },
+    /// Scouts for nodes with the configuration of the session, until the UI stops receiving.
+    Scout {
+        what: WhatAmIMatcher,
+        reply: mpsc::Sender<Result<Hello>>,
+    },
+    /// Combines the nodes with the link state of the routers into a graph.
+    Topology {
+        reply: mpsc::Sender<Result<Topology>>,
     },
 }
 
 impl Command {
+    /// Reports an error to the UI, unless it is not waiting for the result anymore.
     async fn err(self, err: anyhow::Error) {
         match self {
             Command::Get { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
             }
             Command::Put { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
             }
             Command::Delete { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Publish { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
             }
             Command::Subscribe { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::BenchThroughput { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Ping { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Pong { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Queryable { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::LivelinessToken { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::LivelinessGet { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::LivelinessSubscribe { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
             }
             Command::Zid { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
             }
             Command::Peers { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
             }
             Command::Routers { reply, .. } => {
-                reply.send(Err(err)).await.expect("ui receiver is dropped");
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Nodes { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Scout { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
+            }
+            Command::Topology { reply, .. } => {
+                let _ = reply.send(Err(err)).await;
             }
         }
     }
+
+    /// Resolves once the UI drops the receiving end of the reply channel.
+    async fn closed(&self) {
+        match self {
+            Command::Get { reply, .. } => reply.closed().await,
+            Command::Put { reply, .. } => reply.closed().await,
+            Command::Delete { reply, .. } => reply.closed().await,
+            Command::Publish { reply, .. } => reply.closed().await,
+            Command::Subscribe { reply, .. } => reply.closed().await,
+            Command::BenchThroughput { reply, .. } => reply.closed().await,
+            Command::Ping { reply, .. } => reply.closed().await,
+            Command::Pong { reply, .. } => reply.closed().await,
+            Command::Queryable { reply, .. } => reply.closed().await,
+            Command::LivelinessToken { reply, .. } => reply.closed().await,
+            Command::LivelinessGet { reply, .. } => reply.closed().await,
+            Command::LivelinessSubscribe { reply, .. } => reply.closed().await,
+            Command::Zid { reply, .. } => reply.closed().await,
+            Command::Peers { reply, .. } => reply.closed().await,
+            Command::Routers { reply, .. } => reply.closed().await,
+            Command::Nodes { reply, .. } => reply.closed().await,
+            Command::Scout { reply, .. } => reply.closed().await,
+            Command::Topology { reply, .. } => reply.closed().await,
+        }
+    }
 }
 
-pub fn start_handler(session: Session) -> mpsc::Sender<Command> {
+/// Starts handling the commands sent by the UI, the keys of the samples they receive are added
+/// to `keys`. The session was opened with `config`, which scouting starts from too.
+pub fn start_handler(
+    session: Session,
+    config: Config,
+    keys: Keys,
+) -> Result<mpsc::Sender<Command>> {
+    let connections = Connections::watch(&session)?;
+    let mode = config
+        .get_json("mode")
+        .ok()
+        .and_then(|mode| serde_json::from_str::<String>(&mode).ok())
+        .unwrap_or_else(|| "peer".to_string());
+    let config = Arc::new(config);
     let (tx, mut rx) = mpsc::channel(8);
 
     tokio::spawn(async move {
+        // Each command runs in its own task, which is cancelled once the UI drops its reply
+        // channel. The remaining tasks are aborted when the handler stops. Tasks are only
+        // cancelled at their await points, so a loop must await something that yields on each
+        // iteration, resolved futures like a zenoh `put` don't.
+        let mut tasks = JoinSet::new();
         while let Some(cmd) = rx.recv().await {
-            if let Err(err) = handle(&session, &cmd).await {
-                cmd.err(err).await;
-            };
+            let session = session.clone();
+            let connections = connections.clone();
+            let config = config.clone();
+            let mode = mode.clone();
+            let keys = keys.clone();
+            tasks.spawn(async move {
+                tokio::select! {
+                    res = handle(&session, &connections, &config, &mode, &keys, &cmd) => {
+                        if let Err(err) = res {
+                            cmd.err(err).await;
+                        }
+                    }
+                    _ = cmd.closed() => {}
+                }
+            });
+
+            while tasks.try_join_next().is_some() {}
         }
     });
 
-    tx
+    Ok(tx)
+}
+
+/// Sends the `Hello` of each node that replies to scouting, it doesn't need a session.
+pub async fn scout(
+    config: Config,
+    what: WhatAmIMatcher,
+    reply: &mpsc::Sender<Result<Hello>>,
+) -> Result<()> {
+    let scout = zenoh::scout(what, config)
+        .await
+        .map_err(|err| anyhow!("failed to scout: {err}"))?;
+
+    while let Ok(hello) = scout.recv_async().await {
+        reply.send(Ok(hello)).await?;
+    }
+
+    Ok(())
+}
+
+/// When the session connected to each node, to tell how long they've been connected.
+#[derive(Clone)]
+struct Connections {
+    /// The nodes connected while opening the session aren't seen connecting, they're assumed to
+    /// be connected since then.
+    opened: Instant,
+    since: Arc<Mutex<HashMap<String, Instant>>>,
+}
+
+impl Connections {
+    fn watch(session: &Session) -> Result<Self> {
+        let connections = Connections {
+            opened: Instant::now(),
+            since: Arc::default(),
+        };
+
+        // The session publishes its transports opening and closing in its admin space.
+        let since = connections.since.clone();
+        session
+            .declare_subscriber(format!("@/{}/session/transport/unicast/*", session.zid()))
+            .callback(move |sample| {
+                let Some(zid) = sample.key_expr().as_str().rsplit('/').next() else {
+                    return;
+                };
+                let mut since = since.lock().expect("connections lock poisoned");
+                match sample.kind() {
+                    SampleKind::Put => {
+                        since.insert(zid.to_string(), Instant::now());
+                    }
+                    SampleKind::Delete => {
+                        since.remove(zid);
+                    }
+                }
+            })
+            .background()
+            .wait()
+            .map_err(|err| anyhow!("failed to watch connections: {err}"))?;
+
+        Ok(connections)
+    }
+
+    fn age(&self, zid: &str) -> Duration {
+        let since = self.since.lock().expect("connections lock poisoned");
+        since.get(zid).unwrap_or(&self.opened).elapsed()
+    }
+}
+
+/// Finds the nodes the session is connected to, enriched with what the routers report in their
+/// admin space if it's enabled.
+async fn nodes(session: &Session, connections: &Connections, mode: &str) -> Result<Vec<Node>> {
+    let zid = session.zid().to_string();
+    let mut transports = Vec::new();
+    let mut links = HashMap::<String, Vec<admin::Link>>::new();
+    for sample in admin_get(session, &admin::transports(&zid)).await? {
+        let keyexpr = sample.key_expr().as_str();
+        match keyexpr.split('/').collect::<Vec<_>>()[..] {
+            [.., "unicast", _] => {
+                transports.push(admin::parse::<admin::Transport>(keyexpr, sample.payload())?);
+            }
+            [.., "unicast", peer, "link", _] => links
+                .entry(peer.to_string())
+                .or_default()
+                .push(admin::parse(keyexpr, sample.payload())?),
+            _ => {}
+        }
+    }
+    let mut routers = admin_get(session, admin::ROUTERS)
+        .await?
+        .iter()
+        .map(|sample| admin::parse::<admin::Router>(sample.key_expr().as_str(), sample.payload()))
+        .collect::<Result<Vec<_>>>()?;
+    routers.sort_by(|a, b| a.zid.cmp(&b.zid));
+    let locators = |zid: &str| {
+        routers
+            .iter()
+            .find(|router| router.zid == zid)
+            .map(|router| router.locators.clone())
+            .unwrap_or_default()
+    };
+
+    let mut nodes = vec![Node {
+        locators: locators(&zid),
+        zid,
+        whatami: mode.to_string(),
+        protocols: Vec::new(),
+        age: None,
+        connection: Connection::Local,
+    }];
+
+    transports.sort_by(|a, b| a.zid.cmp(&b.zid));
+    for transport in transports {
+        let links = links.remove(&transport.zid).unwrap_or_default();
+        nodes.push(Node {
+            locators: locators(&transport.zid),
+            protocols: protocols(links.iter().map(|link| link.dst.as_str())),
+            age: Some(connections.age(&transport.zid)),
+            zid: transport.zid,
+            whatami: transport.whatami,
+            connection: Connection::Direct,
+        });
+    }
+
+    for router in &routers {
+        let mut sessions = router.sessions.iter().collect::<Vec<_>>();
+        sessions.sort_by(|a, b| a.peer.cmp(&b.peer));
+        for session in sessions {
+            if nodes.iter().any(|node| node.zid == session.peer) {
+                continue;
+            }
+            nodes.push(Node {
+                zid: session.peer.clone(),
+                whatami: session.whatami.clone(),
+                locators: locators(&session.peer),
+                protocols: protocols(session.links.iter().map(|link| link.src.as_str())),
+                age: None,
+                connection: Connection::Via(router.zid.clone()),
+            });
+        }
+    }
+
+    Ok(nodes)
+}
+
+/// The distinct protocols of the given locators.
+fn protocols<'a>(locators: impl Iterator<Item = &'a str>) -> Vec<String> {
+    let mut protocols = Vec::new();
+    for protocol in locators.map(admin::protocol) {
+        if !protocols.iter().any(|known| known == protocol) {
+            protocols.push(protocol.to_string());
+        }
+    }
+    protocols
 }
 
-async fn handle(session: &Session, cmd: &Command) -> Result<()> {
+/// Queries the admin space of every node that replies, error replies are skipped.
+async fn admin_get(session: &Session, selector: &str) -> Result<Vec<zenoh::sample::Sample>> {
+    let replies = session
+        .get(selector)
+        .target(QueryTarget::All)
+        .await
+        .map_err(|err| anyhow!("failed to query {selector}: {err}"))?;
+
+    let mut samples = Vec::new();
+    while let Ok(reply) = replies.recv_async().await {
+        if let Ok(sample) = reply.into_result() {
+            samples.push(sample);
+        }
+    }
+    Ok(samples)
+}
+
+/// Benchmark and ping payloads start with their sequence number, to count the dropped ones or
+/// to match pongs with their ping.
+pub const SEQUENCE_LEN: usize = 8;
+
+/// A benchmark subscriber stops once it doesn't receive samples for this long.
+const BENCH_IDLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Reads the sequence number a benchmark payload starts with.
+fn sequence(sample: &zenoh::sample::Sample) -> Option<u64> {
+    let payload = sample.payload().to_bytes();
+    let seq = payload.get(..SEQUENCE_LEN)?.try_into().ok()?;
+    Some(u64::from_le_bytes(seq))
+}
+
+/// How often a benchmark subscriber checks whether it's done.
+const BENCH_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The samples received by a benchmark subscriber so far.
+#[derive(Default)]
+struct Received {
+    first: Option<Instant>,
+    last: Option<Instant>,
+    samples: u64,
+    bytes: u64,
+    next: Option<u64>,
+    dropped: u64,
+}
+
+impl Received {
+    fn record(&mut self, sample: &zenoh::sample::Sample, duration: Duration) {
+        let now = Instant::now();
+        let first = *self.first.get_or_insert(now);
+        if now.duration_since(first) > duration {
+            return;
+        }
+
+        self.last = Some(now);
+        self.samples += 1;
+        self.bytes += sample.payload().len() as u64;
+        let seq = sequence(sample);
+        if let (Some(seq), Some(next)) = (seq, self.next) {
+            self.dropped += seq.saturating_sub(next);
+        }
+        self.next = seq.map(|seq| seq + 1);
+    }
+
+    fn throughput(&self) -> Throughput {
+        Throughput {
+            role: BenchRole::Subscriber,
+            samples: self.samples,
+            bytes: self.bytes,
+            elapsed: self
+                .first
+                .zip(self.last)
+                .map_or(Duration::ZERO, |(first, last)| last.duration_since(first)),
+            dropped: Some(self.dropped),
+        }
+    }
+}
+
+async fn handle(
+    session: &Session,
+    connections: &Connections,
+    config: &Config,
+    mode: &str,
+    keys: &Keys,
+    cmd: &Command,
+) -> Result<()> {
     match cmd {
-        Command::Get { selector, reply } => {
-            let replies = session
+        Command::Get {
+            selector,
+            options,
+            reply,
+        } => {
+            let mut get = session
                 .get(selector)
+                .target(options.target)
+                .consolidation(options.consolidation);
+            if let Some(timeout) = options.timeout {
+                get = get.timeout(timeout);
+            }
+            if let Some((payload, encoding)) = &options.payload {
+                get = get.payload(payload.clone()).encoding(encoding.clone());
+            }
+            if let Some(attachment) = &options.attachment {
+                get = get.attachment(attachment.clone());
+            }
+            let replies = get
                 .await
                 .map_err(|err| anyhow!("failed to query {selector}: {err}"))?;
 
             while let Ok(response) = replies.recv_async().await {
-                let sample = response.into_result()?;
-                reply
-                    .send(Ok((
-                        sample.key_expr().to_string(),
-                        sample.payload().to_owned(),
-                    )))
-                    .await?;
+                let replier = response.replier_id().map(|id| id.zid().to_string());
+                let result = match response.into_result() {
+                    Ok(sample) => {
+                        keys.insert(sample.key_expr().as_str());
+                        Ok(Sample::from(sample))
+                    }
+                    Err(err) => Err(ReplyError {
+                        payload: err.payload().to_owned(),
+                        encoding: err.encoding().to_owned(),
+                    }),
+                };
+                reply.send(Ok(Reply { result, replier })).await?;
             }
         }
         Command::Put {
             keyexpr,
             payload,
+            encoding,
+            options,
             reply,
         } => {
-            session
-                .put(keyexpr, payload)
-                .encoding(Encoding::TEXT_PLAIN)
-                .await
+            let mut put = session
+                .put(keyexpr, payload.clone())
+                .encoding(encoding.clone())
+                .priority(options.priority)
+                .congestion_control(options.congestion_control)
+                .express(options.express)
+                .reliability(options.reliability);
+            if let Some(attachment) = &options.attachment {
+                put = put.attachment(attachment.clone());
+            }
+            if options.timestamp {
+                put = put.timestamp(session.new_timestamp());
+            }
+            put.await
                 .map_err(|err| anyhow!("failed to put {keyexpr}: {err}"))?;
             reply.send(Ok(())).await?;
         }
-        Command::Delete { keyexpr, reply } => {
-            session
+        Command::Delete {
+            keyexpr,
+            options,
+            reply,
+        } => {
+            let mut delete = session
                 .delete(keyexpr)
+                .priority(options.priority)
+                .congestion_control(options.congestion_control)
+                .express(options.express)
+                .reliability(options.reliability);
+            if let Some(attachment) = &options.attachment {
+                delete = delete.attachment(attachment.clone());
+            }
+            if options.timestamp {
+                delete = delete.timestamp(session.new_timestamp());
+            }
+            delete
                 .await
                 .map_err(|err| anyhow!("failed to delete {keyexpr}: {err}"))?;
             reply.send(Ok(())).await?;
         }
+        Command::Publish {
+            keyexpr,
+            payloads,
+            encoding,
+            options,
+            schedule,
+            reply,
+        } => {
+            let publisher = session
+                .declare_publisher(keyexpr)
+                .encoding(encoding.clone())
+                .priority(options.priority)
+                .congestion_control(options.congestion_control)
+                .express(options.express)
+                .reliability(options.reliability)
+                .await
+                .map_err(|err| anyhow!("failed to declare publisher on {keyexpr}: {err}"))?;
+
+            let (mut lines, template) = match payloads {
+                payload::Payloads::Lines(source) => (
+                    Some(payload::Lines::open(source, schedule.repeat).await?),
+                    None,
+                ),
+                payload::Payloads::Template(template) => (None, Some(template)),
+            };
+            let mut ticker = schedule.period.map(|period| {
+                let mut ticker = time::interval(period);
+                ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+                ticker
+            });
+            let mut published = 0;
+            while schedule.count.is_none_or(|count| published < count) {
+                let payload = match (&mut lines, template) {
+                    (Some(lines), _) => match lines.next().await? {
+                        Some(line) => line,
+                        None => break,
+                    },
+                    (None, Some(template)) => template.render(published),
+                    (None, None) => unreachable!("payloads must come from lines or a template"),
+                };
+                match &mut ticker {
+                    Some(ticker) => {
+                        ticker.tick().await;
+                    }
+                    // Replayed lines and puts don't yield, the task couldn't be cancelled.
+                    None => task::yield_now().await,
+                }
+
+                let mut put = publisher.put(payload);
+                if let Some(attachment) = &options.attachment {
+                    put = put.attachment(attachment.clone());
+                }
+                if options.timestamp {
+                    put = put.timestamp(session.new_timestamp());
+                }
+                put.await
+                    .map_err(|err| anyhow!("failed to publish on {keyexpr}: {err}"))?;
+                published += 1;
+            }
+            reply.send(Ok(published)).await?;
+        }
         Command::Subscribe { keyexpr, reply } => {
             let subscriber = session
                 .declare_subscriber(keyexpr)
                 .await
                 .map_err(|err| anyhow!("failed to subscribe to {keyexpr}: {err}"))?;
 
-            loop {
-                if reply.is_closed() {
-                    break;
+            while let Ok(sample) = subscriber.recv_async().await {
+                keys.insert(sample.key_expr().as_str());
+                reply.send(Ok(Sample::from(sample))).await?;
+            }
+        }
+        Command::BenchThroughput {
+            keyexpr,
+            publish,
+            subscribe,
+            size,
+            duration,
+            reply,
+        } => {
+            // Samples are counted in a callback, a channel would block the local publisher
+            // once it's full.
+            let received = Arc::new(Mutex::new(Received::default()));
+            let _subscriber = if *subscribe {
+                let received = received.clone();
+                let duration = *duration;
+                Some(
+                    session
+                        .declare_subscriber(keyexpr)
+                        .callback(move |sample| {
+                            received
+                                .lock()
+                                .expect("received samples must not be poisoned")
+                                .record(&sample, duration);
+                        })
+                        .await
+                        .map_err(|err| anyhow!("failed to subscribe to {keyexpr}: {err}"))?,
+                )
+            } else {
+                None
+            };
+            let publisher = if *publish {
+                Some(
+                    session
+                        .declare_publisher(keyexpr)
+                        .congestion_control(CongestionControl::Block)
+                        .await
+                        .map_err(|err| {
+                            anyhow!("failed to declare publisher on {keyexpr}: {err}")
+                        })?,
+                )
+            } else {
+                None
+            };
+
+            let publishing = async {
+                let Some(publisher) = publisher else {
+                    return Ok(None);
+                };
+                let mut payload = vec![0; *size];
+                let start = Instant::now();
+                let mut samples: u64 = 0;
+                while start.elapsed() < *duration {
+                    payload[..SEQUENCE_LEN].copy_from_slice(&samples.to_le_bytes());
+                    publisher
+                        .put(payload.clone())
+                        .await
+                        .map_err(|err| anyhow!("failed to publish on {keyexpr}: {err}"))?;
+                    samples += 1;
+                    // Puts don't yield, the subscriber side couldn't check its samples and the
+                    // task couldn't be cancelled.
+                    task::yield_now().await;
+                }
+                anyhow::Ok(Some(Throughput {
+                    role: BenchRole::Publisher,
+                    samples,
+                    bytes: samples * payload.len() as u64,
+                    elapsed: start.elapsed(),
+                    dropped: None,
+                }))
+            };
+            let receiving = async {
+                if !*subscribe {
+                    return None;
                 }
+                // The measure starts with the first sample, and stops after the duration or once
+                // samples stop coming. The local publisher is waited for only while it runs.
+                let deadline = publish.then(|| Instant::now() + *duration + BENCH_IDLE_TIMEOUT);
+                loop {
+                    time::sleep(BENCH_POLL_INTERVAL).await;
+                    let received = received
+                        .lock()
+                        .expect("received samples must not be poisoned");
+                    let done = match (received.first, received.last) {
+                        (Some(first), Some(last)) => {
+                            last.duration_since(first) >= *duration
+                                || last.elapsed() >= BENCH_IDLE_TIMEOUT
+                        }
+                        _ => deadline.is_some_and(|deadline| Instant::now() >= deadline),
+                    };
+                    if done {
+                        break Some(received.throughput());
+                    }
+                }
+            };
 
-                match subscriber.try_recv() {
-                    Ok(Some(sample)) => {
-                        reply
-                            .send(Ok((
-                                sample.key_expr().to_string(),
-                                sample.payload().to_owned(),
-                            )))
-                            .await?;
+            let (published, received) = tokio::join!(publishing, receiving);
+            if let Some(published) = published? {
+                reply.send(Ok(published)).await?;
+            }
+            if let Some(received) = received {
+                reply.send(Ok(received)).await?;
+            }
+        }
+        Command::Ping {
+            keyexpr,
+            count,
+            size,
+            interval,
+            timeout,
+            reply,
+        } => {
+            let publisher = session
+                .declare_publisher(format!("{keyexpr}/ping"))
+                .congestion_control(CongestionControl::Block)
+                .express(true)
+                .await
+                .map_err(|err| anyhow!("failed to declare publisher on {keyexpr}/ping: {err}"))?;
+            let subscriber = session
+                .declare_subscriber(format!("{keyexpr}/pong"))
+                .await
+                .map_err(|err| anyhow!("failed to subscribe to {keyexpr}/pong: {err}"))?;
+
+            let mut payload = vec![0; *size];
+            let mut ticker = time::interval(*interval);
+            ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            let mut seq = 0;
+            while count.is_none_or(|count| seq < count) {
+                ticker.tick().await;
+                payload[..SEQUENCE_LEN].copy_from_slice(&seq.to_le_bytes());
+                let start = Instant::now();
+                publisher
+                    .put(payload.clone())
+                    .await
+                    .map_err(|err| anyhow!("failed to publish on {keyexpr}/ping: {err}"))?;
+
+                // Pongs of earlier pings that timed out are skipped.
+                let rtt = time::timeout(*timeout, async {
+                    while let Ok(sample) = subscriber.recv_async().await {
+                        if sequence(&sample) == Some(seq) {
+                            return Some(start.elapsed());
+                        }
                     }
-                    Ok(None) => {
-                        time::sleep(Duration::from_millis(1)).await;
-                        continue;
+                    None
+                })
+                .await
+                .ok()
+                .flatten();
+                reply
+                    .send(Ok(Pong {
+                        seq,
+                        size: payload.len(),
+                        rtt,
+                    }))
+                    .await?;
+                seq += 1;
+            }
+        }
+        Command::Pong { keyexpr, reply } => {
+            let publisher = session
+                .declare_publisher(format!("{keyexpr}/pong"))
+                .congestion_control(CongestionControl::Block)
+                .express(true)
+                .await
+                .map_err(|err| anyhow!("failed to declare publisher on {keyexpr}/pong: {err}"))?;
+            // Pings are echoed right from the callback to keep the latency low.
+            let _subscriber = session
+                .declare_subscriber(format!("{keyexpr}/ping"))
+                .callback(move |sample| {
+                    let _ = publisher.put(sample.payload().clone()).wait();
+                })
+                .await
+                .map_err(|err| anyhow!("failed to subscribe to {keyexpr}/ping: {err}"))?;
+            reply.send(Ok(())).await?;
+
+            // The subscriber is undeclared once the command is cancelled.
+            future::pending::<()>().await;
+        }
+        Command::Queryable {
+            keyexpr,
+            replies,
+            reply,
+        } => {
+            let table = match replies {
+                Replies::Payload(_) => Vec::new(),
+                Replies::Table(entries) => entries
+                    .iter()
+                    .map(|(key, value)| {
+                        KeyExpr::try_from(key.as_str())
+                            .map(|key| (key, value))
+                            .map_err(|err| anyhow!("invalid key expression {key}: {err}"))
+                    })
+                    .collect::<Result<Vec<_>>>()?,
+            };
+
+            let queryable = session
+                .declare_queryable(keyexpr)
+                .await
+                .map_err(|err| anyhow!("failed to declare queryable on {keyexpr}: {err}"))?;
+
+            while let Ok(query) = queryable.recv_async().await {
+                reply
+                    .send(Ok(Query {
+                        keyexpr: query.key_expr().to_string(),
+                        parameters: query.parameters().to_string(),
+                        payload: query.payload().cloned().zip(query.encoding().cloned()),
+                    }))
+                    .await?;
+
+                match replies {
+                    Replies::Payload(payload) => {
+                        query
+                            .reply(query.key_expr(), payload.as_str())
+                            .encoding(Encoding::TEXT_PLAIN)
+                            .await
+                            .map_err(|err| anyhow!("failed to reply to {keyexpr}: {err}"))?;
+                    }
+                    Replies::Table(_) => {
+                        for (key, value) in table.iter() {
+                            if !key.intersects(query.key_expr()) {
+                                continue;
+                            }
+                            query
+                                .reply(key, value.as_str())
+                                .encoding(Encoding::TEXT_PLAIN)
+                                .await
+                                .map_err(|err| anyhow!("failed to reply to {key}: {err}"))?;
+                        }
                     }
-                    Err(_) => break,
                 }
             }
         }
+        Command::LivelinessToken { keyexpr, reply } => {
+            let _token = session
+                .liveliness()
+                .declare_token(keyexpr)
+                .await
+                .map_err(|err| anyhow!("failed to declare liveliness token {keyexpr}: {err}"))?;
+            reply.send(Ok(())).await?;
+
+            // The token is undeclared once the command is cancelled.
+            future::pending::<()>().await;
+        }
+        Command::LivelinessGet { keyexpr, reply } => {
+            let replies = session
+                .liveliness()
+                .get(keyexpr)
+                .await
+                .map_err(|err| anyhow!("failed to query liveliness of {keyexpr}: {err}"))?;
+
+            while let Ok(response) = replies.recv_async().await {
+                let sample = response.into_result()?;
+                keys.insert(sample.key_expr().as_str());
+                reply.send(Ok(sample.key_expr().to_string())).await?;
+            }
+        }
+        Command::LivelinessSubscribe {
+            keyexpr,
+            history,
+            reply,
+        } => {
+            let subscriber = session
+                .liveliness()
+                .declare_subscriber(keyexpr)
+                .history(*history)
+                .await
+                .map_err(|err| anyhow!("failed to subscribe to liveliness of {keyexpr}: {err}"))?;
+
+            while let Ok(sample) = subscriber.recv_async().await {
+                keys.insert(sample.key_expr().as_str());
+                reply
+                    .send(Ok((sample.key_expr().to_string(), sample.kind())))
+                    .await?;
+            }
+        }
         Command::Zid { reply } => {
             let zid = session.zid().to_string();
             reply.send(Ok(zid)).await?;
         }
         Command::Peers { reply } => {
-            let mut peers = session.info().peers_zid().await;
-            while let Some(peer) = peers.next() {
-                reply.send(Ok(peer.to_string())).await?;
+            for node in nodes(session, connections, mode).await? {
+                if matches!(node.connection, Connection::Direct) && node.whatami == "peer" {
+                    reply.send(Ok(node)).await?;
+                }
             }
         }
         Command::Routers { reply } => {
-            let mut routers = session.info().routers_zid().await;
-            while let Some(router) = routers.next() {
-                reply.send(Ok(router.to_string())).await?;
+            for node in nodes(session, connections, mode).await? {
+                if matches!(node.connection, Connection::Direct) && node.whatami == "router" {
+                    reply.send(Ok(node)).await?;
+                }
+            }
+        }
+        Command::Nodes { reply } => {
+            for node in nodes(session, connections, mode).await? {
+                reply.send(Ok(node)).await?;
             }
         }
+        Command::Scout { what, reply } => {
+            scout(config.clone(), *what, reply).await?;
+        }
+        Command::Topology { reply } => {
+            let nodes = nodes(session, connections, mode).await?;
+            let mut graphs = admin_get(session, admin::LINKSTATE)
+                .await?
+                .iter()
+                .map(|sample| admin::Graph::parse(sample.key_expr().as_str(), sample.payload()))
+                .collect::<Result<Vec<_>>>()?;
+            graphs.sort_by(|a, b| (&a.zid, &a.network).cmp(&(&b.zid, &b.network)));
+            reply.send(Ok(Topology::new(nodes, graphs))).await?;
+        }
     }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn open() -> (Session, mpsc::Sender<Command>) {
+        let mut config = Config::default();
+        config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .unwrap();
+        config.insert_json5("listen/endpoints", "[]").unwrap();
+        let session = zenoh::open(config.clone()).await.unwrap();
+        let commands = start_handler(session.clone(), config, Keys::default()).unwrap();
+        (session, commands)
+    }
+
+    /// Waits until a subscriber on `keyexpr` is declared or undeclared.
+    async fn wait_matching(session: &Session, keyexpr: &str, matching: bool) {
+        let publisher = session
+            .declare_publisher(keyexpr.to_string())
+            .await
+            .unwrap();
+        time::timeout(Duration::from_secs(5), async {
+            while publisher.matching_status().await.unwrap().matching() != matching {
+                time::sleep(Duration::from_millis(10)).await;
+            }
+        })
+        .await
+        .expect("subscriber must be (un)declared in time");
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn handles_commands_while_others_run_and_stops_them_once_dropped() {
+        let (session, commands) = open().await;
+
+        let (tx, mut samples) = mpsc::channel(8);
+        commands
+            .send(Command::Subscribe {
+                keyexpr: "test/handler".to_string(),
+                reply: tx,
+            })
+            .await
+            .unwrap();
+        wait_matching(&session, "test/handler", true).await;
+
+        let (tx, mut zid) = mpsc::channel(1);
+        commands.send(Command::Zid { reply: tx }).await.unwrap();
+        assert_eq!(
+            zid.recv().await.unwrap().unwrap(),
+            session.zid().to_string()
+        );
+
+        session.put("test/handler", "hello").await.unwrap();
+        let sample = samples.recv().await.unwrap().unwrap();
+        assert_eq!(sample.keyexpr, "test/handler");
+
+        drop(samples);
+        wait_matching(&session, "test/handler", false).await;
+    }
+}
@@ -0,0 +1,41 @@
+use std::fmt;
+
+/// Exit codes of one-shot mode for specific failures, other errors exit with `1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Code {
+    /// The Zenoh session couldn't be opened.
+    ConnectionFailure = 2,
+    /// A query didn't receive any replies before timing out.
+    NoReplies = 3,
+    /// A query received an error reply.
+    ErrorReply = 4,
+}
+
+impl Code {
+    /// Creates an error with the given message that exits with this code.
+    pub fn error<M>(self, message: M) -> anyhow::Error
+    where
+        M: fmt::Display + Send + Sync + 'static,
+    {
+        anyhow::Error::new(self).context(message)
+    }
+
+    /// Finds the exit code of the error.
+    pub fn of(err: &anyhow::Error) -> u8 {
+        err.chain()
+            .find_map(|err| err.downcast_ref::<Code>())
+            .map_or(1, |code| *code as u8)
+    }
+}
+
+impl fmt::Display for Code {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Code::ConnectionFailure => write!(f, "connection failure"),
+            Code::NoReplies => write!(f, "no replies"),
+            Code::ErrorReply => write!(f, "error reply"),
+        }
+    }
+}
+
+impl std::error::Error for Code {}
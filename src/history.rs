@@ -0,0 +1,48 @@
+use std::env;
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use anyhow::{Result, anyhow};
+use rustyline::error::ReadlineError;
+use rustyline::history::{DefaultHistory, History};
+
+/// How many lines are kept, the oldest ones are dropped first.
+pub const MAX_SIZE: usize = 1000;
+
+/// The file the lines read in interactive mode are kept in across sessions,
+/// `$XDG_DATA_HOME/zenoh-cli/history` or `~/.local/share/zenoh-cli/history`.
+pub fn path() -> Option<PathBuf> {
+    let absolute = |var| {
+        env::var_os(var)
+            .map(PathBuf::from)
+            .filter(|path| path.is_absolute())
+    };
+    let data =
+        absolute("XDG_DATA_HOME").or_else(|| Some(absolute("HOME")?.join(".local/share")))?;
+    Some(data.join("zenoh-cli").join("history"))
+}
+
+/// Loads the history from the file, which doesn't exist until a line is read the first time.
+pub fn load(history: &mut DefaultHistory, path: &Path) -> Result<()> {
+    match history.load(path) {
+        Ok(()) => Ok(()),
+        Err(ReadlineError::Io(err)) if err.kind() == io::ErrorKind::NotFound => Ok(()),
+        Err(err) => Err(anyhow!(
+            "failed to load history from {}: {err}",
+            path.display()
+        )),
+    }
+}
+
+/// Appends the lines read since the last time to the file, keeping the lines of the other
+/// sessions written in the meantime.
+pub fn append(history: &mut DefaultHistory, path: &Path) -> Result<()> {
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|err| anyhow!("failed to create {}: {err}", dir.display()))?;
+    }
+    history
+        .append(path)
+        .map_err(|err| anyhow!("failed to save history to {}: {err}", path.display()))
+}
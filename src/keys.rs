@@ -0,0 +1,79 @@
+use std::collections::BTreeSet;
+use std::sync::{Arc, Mutex};
+
+/// How many keys are kept, the ones seen after are ignored so busy subscriptions don't grow it
+/// forever.
+const MAX_KEYS: usize = 10_000;
+
+/// The keys of the samples the session has seen, to complete key expressions in interactive mode.
+#[derive(Clone, Default)]
+pub struct Keys(Arc<Mutex<BTreeSet<String>>>);
+
+impl Keys {
+    pub fn insert(&self, key: &str) {
+        let mut keys = self.0.lock().expect("keys lock poisoned");
+        if keys.len() < MAX_KEYS && !keys.contains(key) {
+            keys.insert(key.to_string());
+        }
+    }
+
+    /// Completes the prefix up to the end of the next segment of the known keys, e.g.
+    /// `demo/kitchen/` and `demo/kid` for `demo/ki`. The segment keeps its trailing `/` if
+    /// there are keys below it, so the completion can go on from there.
+    pub fn complete(&self, prefix: &str) -> Vec<String> {
+        let keys = self.0.lock().expect("keys lock poisoned");
+        let mut completions = keys
+            .range(prefix.to_string()..)
+            .take_while(|key| key.starts_with(prefix))
+            .map(|key| match key[prefix.len()..].find('/') {
+                Some(end) => key[..prefix.len() + end + 1].to_string(),
+                None => key.clone(),
+            })
+            .collect::<Vec<_>>();
+        completions.dedup();
+        completions
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(keys: &[&str]) -> Keys {
+        let known = Keys::default();
+        for key in keys {
+            known.insert(key);
+        }
+        known
+    }
+
+    #[test]
+    fn completes_up_to_the_next_segment() {
+        let keys = keys(&["demo/kitchen/temp", "demo/kid", "demo/garage"]);
+        assert_eq!(keys.complete("demo/ki"), ["demo/kid", "demo/kitchen/"]);
+        assert_eq!(keys.complete("demo/kitchen/"), ["demo/kitchen/temp"]);
+        assert_eq!(keys.complete(""), ["demo/"]);
+    }
+
+    #[test]
+    fn completes_each_segment_once() {
+        let keys = keys(&[
+            "demo/kitchen/humidity",
+            "demo/kitchen/light",
+            "demo/kitchen/temp",
+        ]);
+        assert_eq!(keys.complete("demo/k"), ["demo/kitchen/"]);
+    }
+
+    #[test]
+    fn completes_a_key_that_also_has_keys_below_it() {
+        let keys = keys(&["demo/kitchen", "demo/kitchen/temp"]);
+        assert_eq!(keys.complete("demo/kit"), ["demo/kitchen", "demo/kitchen/"]);
+    }
+
+    #[test]
+    fn completes_nothing_for_unknown_keys() {
+        let keys = keys(&["demo/kitchen/temp"]);
+        assert!(keys.complete("demo/garage").is_empty());
+    }
+}
@@ -1,32 +1,162 @@
-use std::env;
+use std::path::PathBuf;
+use std::process::ExitCode;
 
-use anyhow::{Result, anyhow};
+use anyhow::{Result, anyhow, bail};
+use clap::{Parser, ValueEnum};
 use zenoh::Config;
 
+mod admin;
+mod args;
 mod command;
+mod exit;
+mod history;
+mod keys;
+mod output;
+mod payload;
+mod render;
+mod topology;
 mod ui;
 
+/// A CLI for Zenoh, starts in interactive mode if no command is given.
+#[derive(Parser)]
+#[command(version)]
+struct Cli {
+    /// The Zenoh session mode.
+    #[arg(short, long)]
+    mode: Option<Mode>,
+    /// Endpoints to connect to, e.g. `tcp/127.0.0.1:7447`.
+    #[arg(short = 'e', long)]
+    connect: Vec<String>,
+    /// Endpoints to listen on, e.g. `tcp/0.0.0.0:7447`.
+    #[arg(short, long)]
+    listen: Vec<String>,
+    /// Disable the multicast-based scouting mechanism.
+    #[arg(long)]
+    no_multicast_scouting: bool,
+    /// Don't keep the history of interactive mode across sessions.
+    #[arg(long)]
+    no_history: bool,
+    /// A configuration file to start with.
+    #[arg(short, long, env = "ZENOH_CONFIG")]
+    config: Option<PathBuf>,
+    /// How the results of commands are printed.
+    #[arg(short, long, value_enum, default_value_t)]
+    format: output::Format,
+    /// Overrides a configuration value, e.g. `--cfg 'timestamping/enabled:true'`.
+    #[arg(long, value_name = "KEY:JSON5")]
+    cfg: Vec<String>,
+    /// The command to run, e.g. `put myhome/kitchen/temp 25`.
+    #[arg(trailing_var_arg = true, allow_hyphen_values = true)]
+    command: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    Peer,
+    Client,
+    Router,
+}
+
+impl Cli {
+    fn config(&self) -> Result<Config> {
+        let mut config = match &self.config {
+            Some(path) => Config::from_file(path)
+                .map_err(|err| anyhow!("failed to load zenoh config: {err}"))?,
+            None => Config::default(),
+        };
+
+        if let Some(mode) = self.mode {
+            let mode = match mode {
+                Mode::Peer => "peer",
+                Mode::Client => "client",
+                Mode::Router => "router",
+            };
+            insert(&mut config, "mode", &serde_json::json!(mode).to_string())?;
+        }
+        if !self.connect.is_empty() {
+            insert(
+                &mut config,
+                "connect/endpoints",
+                &serde_json::json!(self.connect).to_string(),
+            )?;
+        }
+        if !self.listen.is_empty() {
+            insert(
+                &mut config,
+                "listen/endpoints",
+                &serde_json::json!(self.listen).to_string(),
+            )?;
+        }
+        if self.no_multicast_scouting {
+            insert(&mut config, "scouting/multicast/enabled", "false")?;
+        }
+        for cfg in &self.cfg {
+            let Some((key, value)) = cfg.split_once(':') else {
+                bail!("invalid config override: {cfg}, expected <key>:<json5 value>");
+            };
+            insert(&mut config, key, value)?;
+        }
+
+        Ok(config)
+    }
+}
+
+fn insert(config: &mut Config, key: &str, value: &str) -> Result<()> {
+    config
+        .insert_json5(key, value)
+        .map_err(|err| anyhow!("failed to set {key} in zenoh config: {err}"))
+}
+
 #[tokio::main]
-async fn main() -> Result<()> {
-    let args: Vec<_> = env::args().skip(1).collect();
+async fn main() -> ExitCode {
+    // Usage errors exit with 1 instead of clap's 2, which is kept for connection failures.
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(err) => {
+            let _ = err.print();
+            return ExitCode::from(if err.use_stderr() { 1 } else { 0 });
+        }
+    };
+
+    match run(cli).await {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(err) => {
+            eprintln!("error: {err}");
+            ExitCode::from(exit::Code::of(&err))
+        }
+    }
+}
+
+async fn run(cli: Cli) -> Result<()> {
+    let config = cli.config()?;
+
+    // Scouting doesn't need a session, so it also finds the nodes it couldn't connect to.
+    if cli.command.first().is_some_and(|cmd| cmd == "scout") {
+        return ui::scout(config, cli.format, args::Args::new(cli.command)).await;
+    }
 
-    let config = match env::var("ZENOH_CONFIG") {
-        Ok(config) => Config::from_file(config)
-            .map_err(|err| anyhow!("failed to load zenoh config: {err}"))?,
-        Err(_) => Config::default(),
+    let history = if cli.no_history {
+        None
+    } else {
+        history::path()
     };
+    if cli.command.first().is_some_and(|cmd| cmd == "history") {
+        return ui::history(history.as_deref(), cli.format, args::Args::new(cli.command));
+    }
 
-    let session = zenoh::open(config)
-        .await
-        .map_err(|err| anyhow!("failed to create zenoh session: {err}"))?;
+    let session = zenoh::open(config.clone()).await.map_err(|err| {
+        exit::Code::ConnectionFailure.error(format!("failed to create zenoh session: {err}"))
+    })?;
 
-    let tx = command::start_handler(session);
+    let keys = keys::Keys::default();
+    let tx = command::start_handler(session, config, keys.clone())?;
 
+    let (args, format) = (cli.command, cli.format);
     tokio::task::spawn_blocking(async move || {
         if args.is_empty() {
-            ui::start(tx).await
+            ui::start(tx, format, history, keys).await
         } else {
-            ui::handle(&tx, args.join(" ").to_string()).await
+            ui::handle(&tx, format, args::Args::new(args)).await
         }
     })
     .await?
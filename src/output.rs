@@ -0,0 +1,596 @@
+use std::io::{self, Write};
+use std::time::Duration;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use clap::ValueEnum;
+use colored::{ColoredString, Colorize};
+use serde_json::{Map, Value, json};
+use zenoh::bytes::{Encoding, ZBytes};
+use zenoh::qos::{CongestionControl, Priority};
+use zenoh::sample::SampleKind;
+use zenoh::scouting::Hello;
+
+use super::command::{BenchRole, Connection, Node, Pong, Reply, Sample, Throughput};
+use super::render;
+
+/// How the results of commands are printed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// Colored text for humans.
+    #[default]
+    Pretty,
+    /// A JSON array of all the records, printed once the command is done.
+    Json,
+    /// A JSON object per line for each record.
+    Jsonl,
+    /// A CSV row for each record, with a header row.
+    Csv,
+    /// Only the payloads or the IDs, one per line.
+    Raw,
+}
+
+/// Prints the results of a single command in the chosen format.
+pub struct Output {
+    format: Format,
+    mode: render::Mode,
+    verbose: bool,
+    records: Vec<Value>,
+    header: bool,
+}
+
+impl Output {
+    pub fn new(format: Format) -> Self {
+        Output {
+            format,
+            mode: render::Mode::Auto,
+            verbose: false,
+            records: Vec::new(),
+            header: false,
+        }
+    }
+
+    pub fn render(mut self, mode: render::Mode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    pub fn verbose(mut self, verbose: bool) -> Self {
+        self.verbose = verbose;
+        self
+    }
+
+    pub fn sample(&mut self, sample: Sample) {
+        match self.format {
+            Format::Pretty => self.print_sample(sample),
+            Format::Raw => print_raw(&sample.payload),
+            _ => {
+                let record = self.sample_record(sample);
+                self.record(Value::Object(record));
+            }
+        }
+    }
+
+    /// Prints a reply to a query, error replies are printed inline with the samples.
+    pub fn reply(&mut self, reply: Reply) {
+        let replier = reply.replier;
+        match (self.format, reply.result) {
+            (Format::Pretty, Ok(sample)) => {
+                self.print_sample(sample);
+                print_replier(replier);
+            }
+            (Format::Pretty, Err(err)) => {
+                println!(
+                    "{} {}",
+                    "error:".red(),
+                    render_payload(&err.payload, &err.encoding, self.mode).red()
+                );
+                print_field("encoding", err.encoding);
+                print_replier(replier);
+            }
+            (Format::Raw, Ok(sample)) => print_raw(&sample.payload),
+            (Format::Raw, Err(err)) => {
+                eprintln!(
+                    "error: {}",
+                    render::render(&err.payload, &err.encoding, self.mode)
+                );
+            }
+            (_, Ok(sample)) => {
+                let mut record = self.sample_record(sample);
+                record.insert("replier".to_string(), json!(replier));
+                self.record(Value::Object(record));
+            }
+            (_, Err(err)) => {
+                // Error replies have the same fields as samples to keep CSV columns aligned.
+                let mut record = Map::new();
+                record.insert("key".to_string(), Value::Null);
+                record.insert(
+                    "payload".to_string(),
+                    json!(self.payload(&err.payload, &err.encoding)),
+                );
+                record.insert("encoding".to_string(), json!(err.encoding.to_string()));
+                record.insert("timestamp".to_string(), Value::Null);
+                record.insert("kind".to_string(), json!("error"));
+                if self.verbose {
+                    for field in ["priority", "congestion_control", "express", "attachment"] {
+                        record.insert(field.to_string(), Value::Null);
+                    }
+                }
+                record.insert("replier".to_string(), json!(replier));
+                self.record(Value::Object(record));
+            }
+        }
+    }
+
+    /// Prints the ID of a Zenoh node, e.g. for `zid` or `peers`.
+    pub fn zid(&mut self, zid: String) {
+        match self.format {
+            Format::Pretty | Format::Raw => println!("{zid}"),
+            _ => self.record(json!({ "zid": zid })),
+        }
+    }
+
+    /// Prints a node found by scouting.
+    pub fn hello(&mut self, hello: &Hello) {
+        let locators = hello
+            .locators()
+            .iter()
+            .map(|locator| locator.to_string())
+            .collect::<Vec<_>>();
+        match self.format {
+            Format::Pretty => println!(
+                "{}: {} {}",
+                hello.zid().to_string().bright_black(),
+                hello.whatami().to_str(),
+                locators.join(", ")
+            ),
+            Format::Raw => println!("{}", hello.zid()),
+            _ => self.record(json!({
+                "zid": hello.zid().to_string(),
+                "whatami": hello.whatami().to_str(),
+                "locators": locators,
+            })),
+        }
+    }
+
+    /// Prints a line of the history with its number, which starts at 1 for the oldest line.
+    pub fn history(&mut self, number: usize, line: &str) {
+        match self.format {
+            Format::Pretty => println!("{} {line}", format!("{number:>5}").bright_black()),
+            Format::Raw => println!("{line}"),
+            _ => self.record(json!({ "number": number, "line": line })),
+        }
+    }
+
+    /// Prints the nodes known to the session, with how they're reached if `connection` is set.
+    pub fn nodes(&mut self, nodes: Vec<Node>, connection: bool) {
+        if self.format == Format::Raw {
+            for node in nodes {
+                println!("{}", node.zid);
+            }
+            return;
+        }
+
+        let mut columns = vec!["zid", "whatami", "protocols", "locators"];
+        columns.push(if self.format == Format::Pretty {
+            "age"
+        } else {
+            "age_secs"
+        });
+        if connection {
+            columns.push("connection");
+        }
+        let rows = nodes
+            .into_iter()
+            .map(|node| {
+                let age = match (node.age, self.format) {
+                    (Some(age), Format::Pretty) => json!(format_age(age)),
+                    (age, _) => json!(age.map(|age| age.as_secs_f64())),
+                };
+                let mut row = vec![
+                    json!(node.zid),
+                    json!(node.whatami),
+                    json!(node.protocols),
+                    json!(node.locators),
+                    age,
+                ];
+                if connection {
+                    row.push(json!(match node.connection {
+                        Connection::Local => "self".to_string(),
+                        Connection::Direct => "direct".to_string(),
+                        Connection::Via(router) => format!("via {router}"),
+                    }));
+                }
+                row
+            })
+            .collect();
+        self.table(&columns, rows);
+    }
+
+    /// Prints rows under the given columns, aligned for humans and as a record per row otherwise.
+    pub fn table(&mut self, columns: &[&str], rows: Vec<Vec<Value>>) {
+        match self.format {
+            Format::Pretty => {
+                let header = columns
+                    .iter()
+                    .map(|column| column.replace('_', " ").to_uppercase())
+                    .collect::<Vec<_>>();
+                let rows = rows
+                    .iter()
+                    .map(|row| row.iter().map(table_cell).collect::<Vec<_>>())
+                    .collect::<Vec<_>>();
+                let widths = (0..columns.len())
+                    .map(|i| {
+                        rows.iter()
+                            .map(|row| row[i].chars().count())
+                            .chain([header[i].len()])
+                            .max()
+                            .unwrap_or_default()
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", table_row(&header, &widths).bold());
+                for row in rows {
+                    println!("{}", table_row(&row, &widths));
+                }
+            }
+            Format::Raw => {
+                for row in rows {
+                    let row = row.iter().map(table_cell).collect::<Vec<_>>();
+                    println!("{}", row.join("\t"));
+                }
+            }
+            _ => {
+                for row in rows {
+                    let record = columns
+                        .iter()
+                        .map(|column| column.to_string())
+                        .zip(row)
+                        .collect();
+                    self.record(Value::Object(record));
+                }
+            }
+        }
+    }
+
+    /// Prints the result of one side of a throughput benchmark.
+    pub fn throughput(&mut self, throughput: Throughput) {
+        let seconds = throughput.elapsed.as_secs_f64();
+        let (msgs_per_sec, mb_per_sec) = if seconds > 0.0 {
+            (
+                throughput.samples as f64 / seconds,
+                throughput.bytes as f64 / seconds / 1_000_000.0,
+            )
+        } else {
+            (0.0, 0.0)
+        };
+        let role = match throughput.role {
+            BenchRole::Publisher => "publisher",
+            BenchRole::Subscriber => "subscriber",
+        };
+
+        match self.format {
+            Format::Pretty | Format::Raw => {
+                let mut line = format!(
+                    "{role}: {} samples, {} bytes in {seconds:.2}s, {msgs_per_sec:.0} msg/s, {mb_per_sec:.2} MB/s",
+                    throughput.samples, throughput.bytes
+                );
+                if let Some(dropped) = throughput.dropped {
+                    line.push_str(&format!(", {dropped} dropped"));
+                }
+                println!("{line}");
+            }
+            _ => self.record(json!({
+                "role": role,
+                "samples": throughput.samples,
+                "bytes": throughput.bytes,
+                "seconds": seconds,
+                "msgs_per_sec": msgs_per_sec,
+                "mb_per_sec": mb_per_sec,
+                "dropped": throughput.dropped,
+            })),
+        }
+    }
+
+    /// Prints a round trip of `ping`, it's only printed for humans and summarized otherwise.
+    pub fn pong(&self, keyexpr: &str, pong: &Pong) {
+        if !matches!(self.format, Format::Pretty | Format::Raw) {
+            return;
+        }
+        match pong.rtt {
+            Some(rtt) => println!(
+                "{} bytes from {keyexpr}: seq={} time={:.3} ms",
+                pong.size,
+                pong.seq,
+                millis(rtt)
+            ),
+            None => println!(
+                "{}",
+                format!("no pong from {keyexpr}: seq={} timed out", pong.seq).red()
+            ),
+        }
+    }
+
+    /// Prints the statistics of `ping` once it's done, like the Unix `ping`.
+    pub fn ping_stats(&mut self, keyexpr: &str, transmitted: u64, mut rtts: Vec<Duration>) {
+        rtts.sort();
+        let received = rtts.len() as u64;
+        let loss = if transmitted > 0 {
+            (transmitted - received) as f64 / transmitted as f64 * 100.0
+        } else {
+            0.0
+        };
+        let stats = (!rtts.is_empty()).then(|| {
+            let avg = rtts.iter().sum::<Duration>() / rtts.len() as u32;
+            [
+                rtts[0],
+                avg,
+                percentile(&rtts, 50),
+                percentile(&rtts, 99),
+                rtts[rtts.len() - 1],
+            ]
+            .map(millis)
+        });
+
+        match self.format {
+            Format::Pretty | Format::Raw => {
+                println!("--- {keyexpr} ping statistics ---");
+                println!("{transmitted} transmitted, {received} received, {loss:.1}% loss");
+                if let Some([min, avg, p50, p99, max]) = stats {
+                    println!(
+                        "rtt min/avg/p50/p99/max = {min:.3}/{avg:.3}/{p50:.3}/{p99:.3}/{max:.3} ms"
+                    );
+                }
+            }
+            _ => {
+                let [min, avg, p50, p99, max] = stats.map_or([None; 5], |stats| stats.map(Some));
+                self.record(json!({
+                    "keyexpr": keyexpr,
+                    "transmitted": transmitted,
+                    "received": received,
+                    "loss": loss,
+                    "min_ms": min,
+                    "avg_ms": avg,
+                    "p50_ms": p50,
+                    "p99_ms": p99,
+                    "max_ms": max,
+                }));
+            }
+        }
+    }
+
+    /// Prints an informational message for humans, it's skipped in the other formats.
+    pub fn status(&self, status: &str) {
+        if self.format == Format::Pretty {
+            println!("{}", status.bright_black());
+        }
+    }
+
+    /// Prints the records kept until the command is done.
+    pub fn finish(self) {
+        if self.format == Format::Json {
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&self.records).expect("records must be serializable")
+            );
+        }
+    }
+
+    fn sample_record(&self, sample: Sample) -> Map<String, Value> {
+        let mut record = Map::new();
+        record.insert("key".to_string(), json!(sample.keyexpr));
+        record.insert(
+            "payload".to_string(),
+            json!(self.payload(&sample.payload, &sample.encoding)),
+        );
+        record.insert("encoding".to_string(), json!(sample.encoding.to_string()));
+        record.insert(
+            "timestamp".to_string(),
+            json!(sample.timestamp.map(|ts| ts.to_string_rfc3339_lossy())),
+        );
+        record.insert("kind".to_string(), json!(sample_kind(sample.kind)));
+        if self.verbose {
+            record.insert("priority".to_string(), json!(priority(sample.priority)));
+            record.insert(
+                "congestion_control".to_string(),
+                json!(congestion_control(sample.congestion_control)),
+            );
+            record.insert("express".to_string(), json!(sample.express));
+            record.insert(
+                "attachment".to_string(),
+                json!(
+                    sample
+                        .attachment
+                        .map(|attachment| { self.payload(&attachment, &Encoding::ZENOH_BYTES) })
+                ),
+            );
+        }
+        record
+    }
+
+    fn record(&mut self, record: Value) {
+        match self.format {
+            Format::Json => self.records.push(record),
+            Format::Jsonl => println!("{record}"),
+            Format::Csv => {
+                let Value::Object(record) = record else {
+                    unreachable!("records must be objects");
+                };
+                if !self.header {
+                    let header = record.keys().map(|key| csv_field(key)).collect::<Vec<_>>();
+                    println!("{}", header.join(","));
+                    self.header = true;
+                }
+                let row = record
+                    .values()
+                    .map(|value| match value {
+                        Value::Null => String::new(),
+                        Value::String(value) => csv_field(value),
+                        value => csv_field(&value.to_string()),
+                    })
+                    .collect::<Vec<_>>();
+                println!("{}", row.join(","));
+            }
+            Format::Pretty | Format::Raw => unreachable!("records must be printed as text"),
+        }
+    }
+
+    /// Payloads are kept as text if possible in records, and binary ones are encoded with base64.
+    fn payload(&self, payload: &ZBytes, encoding: &Encoding) -> String {
+        match self.mode {
+            render::Mode::Auto => match payload.try_to_string() {
+                Ok(text) => text.into_owned(),
+                Err(_) => BASE64.encode(payload.to_bytes()),
+            },
+            mode => render::render(payload, encoding, mode),
+        }
+    }
+
+    fn print_sample(&self, sample: Sample) {
+        println!(
+            "{}: {}",
+            sample.keyexpr.bright_black(),
+            render_payload(&sample.payload, &sample.encoding, self.mode)
+        );
+        if !self.verbose {
+            return;
+        }
+
+        print_field("kind", kind_name(sample.kind));
+        print_field("encoding", sample.encoding);
+        match sample.timestamp {
+            Some(timestamp) => print_field("timestamp", timestamp.to_string_rfc3339_lossy()),
+            None => print_field("timestamp", "none".bright_black()),
+        }
+        print_field("priority", priority(sample.priority));
+        print_field(
+            "congestion control",
+            congestion_control(sample.congestion_control),
+        );
+        print_field("express", sample.express);
+        match sample.attachment {
+            Some(attachment) => print_field(
+                "attachment",
+                render::render(&attachment, &Encoding::ZENOH_BYTES, self.mode),
+            ),
+            None => print_field("attachment", "none".bright_black()),
+        }
+    }
+}
+
+/// Renders the payload to be printed after a key, multi-line payloads start on their own line.
+pub fn render_payload(payload: &ZBytes, encoding: &Encoding, mode: render::Mode) -> String {
+    let rendered = render::render(payload, encoding, mode);
+    if rendered.contains('\n') {
+        format!("\n{rendered}")
+    } else {
+        rendered
+    }
+}
+
+pub fn kind_name(kind: SampleKind) -> ColoredString {
+    match kind {
+        SampleKind::Put => sample_kind(kind).green(),
+        SampleKind::Delete => sample_kind(kind).red(),
+    }
+}
+
+/// Formats a duration with its two most significant units, e.g. `3m12s`.
+fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    match secs {
+        0..60 => format!("{secs}s"),
+        60..3600 => format!("{}m{}s", secs / 60, secs % 60),
+        3600..86400 => format!("{}h{}m", secs / 3600, secs % 3600 / 60),
+        _ => format!("{}d{}h", secs / 86400, secs % 86400 / 3600),
+    }
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The nearest-rank percentile of sorted durations.
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted.len() * percent).div_ceil(100);
+    sorted[rank.saturating_sub(1)]
+}
+
+/// Formats a value of a table, lists are joined and missing values are shown as `-`.
+fn table_cell(value: &Value) -> String {
+    match value {
+        Value::Null => "-".to_string(),
+        Value::String(value) => value.clone(),
+        Value::Array(values) => {
+            if values.is_empty() {
+                return "-".to_string();
+            }
+            values.iter().map(table_cell).collect::<Vec<_>>().join(", ")
+        }
+        value => value.to_string(),
+    }
+}
+
+/// Pads the cells to the widths of their column, the last one isn't padded.
+fn table_row(cells: &[String], widths: &[usize]) -> String {
+    let mut row = String::new();
+    for (i, (cell, width)) in cells.iter().zip(widths).enumerate() {
+        if i + 1 == cells.len() {
+            row.push_str(cell);
+        } else {
+            row.push_str(&format!("{cell:<width$}  "));
+        }
+    }
+    row
+}
+
+fn print_field(name: &str, value: impl std::fmt::Display) {
+    println!("  {} {}", format!("{name}:").bright_black(), value);
+}
+
+fn print_replier(replier: Option<String>) {
+    match replier {
+        Some(replier) => print_field("replier", replier),
+        None => print_field("replier", "unknown".bright_black()),
+    }
+}
+
+fn print_raw(payload: &ZBytes) {
+    let mut stdout = io::stdout().lock();
+    let _ = stdout.write_all(&payload.to_bytes());
+    let _ = stdout.write_all(b"\n");
+}
+
+fn sample_kind(kind: SampleKind) -> &'static str {
+    match kind {
+        SampleKind::Put => "put",
+        SampleKind::Delete => "delete",
+    }
+}
+
+fn priority(priority: Priority) -> &'static str {
+    match priority {
+        Priority::RealTime => "real-time",
+        Priority::InteractiveHigh => "interactive-high",
+        Priority::InteractiveLow => "interactive-low",
+        Priority::DataHigh => "data-high",
+        Priority::Data => "data",
+        Priority::DataLow => "data-low",
+        Priority::Background => "background",
+    }
+}
+
+fn congestion_control(congestion_control: CongestionControl) -> &'static str {
+    match congestion_control {
+        CongestionControl::Drop => "drop",
+        CongestionControl::Block => "block",
+        CongestionControl::BlockFirst => "block-first",
+    }
+}
+
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
@@ -0,0 +1,270 @@
+use std::fs;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{Context, Result, anyhow, bail};
+use rand::Rng;
+use rand::distr::Alphanumeric;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, BufReader};
+use zenoh::bytes::{Encoding, ZBytes};
+
+/// Where a payload is read from, e.g. `put demo/a @image.png`.
+pub enum Source {
+    /// The argument itself, a leading `@@` is unescaped to `@`.
+    Inline(String),
+    /// A file given as `@path`.
+    File(PathBuf),
+    /// The standard input, given as `-`.
+    Stdin,
+}
+
+impl Source {
+    pub fn new(arg: String) -> Self {
+        if arg == "-" {
+            Source::Stdin
+        } else if let Some(inline) = arg.strip_prefix("@@") {
+            Source::Inline(format!("@{inline}"))
+        } else if let Some(path) = arg.strip_prefix('@') {
+            Source::File(PathBuf::from(path))
+        } else {
+            Source::Inline(arg)
+        }
+    }
+
+    /// Reads the payload as-is, with the encoding inferred from the extension of the file, or
+    /// from whether the payload is text otherwise.
+    pub fn read(self) -> Result<(ZBytes, Encoding)> {
+        match self {
+            Source::Inline(payload) => Ok((ZBytes::from(payload), Encoding::TEXT_PLAIN)),
+            Source::File(path) => {
+                let bytes = fs::read(&path)
+                    .with_context(|| format!("failed to read {}", path.display()))?;
+                let encoding = encoding_of(&path).unwrap_or_else(|| detect_encoding(&bytes));
+                Ok((ZBytes::from(bytes), encoding))
+            }
+            Source::Stdin => {
+                let mut bytes = Vec::new();
+                io::stdin()
+                    .lock()
+                    .read_to_end(&mut bytes)
+                    .context("failed to read stdin")?;
+                let encoding = detect_encoding(&bytes);
+                Ok((ZBytes::from(bytes), encoding))
+            }
+        }
+    }
+}
+
+/// What `pub` publishes.
+pub enum Payloads {
+    /// Each line of a file or stdin.
+    Lines(Source),
+    /// A payload rendered from the template for each publication.
+    Template(Template),
+}
+
+/// A payload with placeholders that are replaced for each publication, e.g. `{"seq": {counter}}`.
+///
+/// The placeholders are `{counter}`, `{timestamp}`, `{random}` and `{random:<len>}`, any other
+/// text is kept as it is.
+pub struct Template(Vec<Part>);
+
+enum Part {
+    Text(String),
+    /// The number of samples published before, starting from 0.
+    Counter,
+    /// Milliseconds since the Unix epoch.
+    Timestamp,
+    /// A random number.
+    Random,
+    /// Random alphanumeric characters of the given length.
+    RandomText(usize),
+}
+
+impl Template {
+    pub fn parse(template: &str) -> Result<Self> {
+        let mut parts = Vec::new();
+        let mut text = String::new();
+        let mut rest = template;
+        while let Some(start) = rest.find('{') {
+            text.push_str(&rest[..start]);
+            rest = &rest[start..];
+            let placeholder = match rest.find('}') {
+                Some(end) => placeholder(&rest[1..end])
+                    .transpose()?
+                    .map(|part| (part, end + 1)),
+                None => None,
+            };
+            match placeholder {
+                Some((part, len)) => {
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(part);
+                    rest = &rest[len..];
+                }
+                None => {
+                    text.push('{');
+                    rest = &rest[1..];
+                }
+            }
+        }
+        text.push_str(rest);
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+        Ok(Template(parts))
+    }
+
+    pub fn render(&self, counter: u64) -> ZBytes {
+        let mut rng = rand::rng();
+        let mut payload = String::new();
+        for part in &self.0 {
+            match part {
+                Part::Text(text) => payload.push_str(text),
+                Part::Counter => payload.push_str(&counter.to_string()),
+                Part::Timestamp => {
+                    let now = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .unwrap_or_default();
+                    payload.push_str(&now.as_millis().to_string());
+                }
+                Part::Random => payload.push_str(&rng.random::<u32>().to_string()),
+                Part::RandomText(len) => payload.extend(
+                    (&mut rng)
+                        .sample_iter(Alphanumeric)
+                        .take(*len)
+                        .map(char::from),
+                ),
+            }
+        }
+        ZBytes::from(payload)
+    }
+}
+
+/// Parses the name of a placeholder, `None` if it's not one so it's kept as text.
+fn placeholder(name: &str) -> Option<Result<Part>> {
+    match name {
+        "counter" => Some(Ok(Part::Counter)),
+        "timestamp" => Some(Ok(Part::Timestamp)),
+        "random" => Some(Ok(Part::Random)),
+        _ => {
+            let len = name.strip_prefix("random:")?;
+            Some(
+                len.parse()
+                    .map(Part::RandomText)
+                    .map_err(|err| anyhow!("invalid length of {{{name}}}: {err}")),
+            )
+        }
+    }
+}
+
+/// Reads payloads line by line from a file or stdin, e.g. for `pub`.
+pub struct Lines {
+    reader: Box<dyn AsyncBufRead + Send + Unpin>,
+    /// The lines read so far, kept to start over once the input is exhausted.
+    history: Option<Vec<ZBytes>>,
+    replayed: Option<usize>,
+}
+
+impl Lines {
+    pub async fn open(source: &Source, repeat: bool) -> Result<Self> {
+        let reader: Box<dyn AsyncBufRead + Send + Unpin> = match source {
+            Source::File(path) => Box::new(BufReader::new(
+                tokio::fs::File::open(path)
+                    .await
+                    .with_context(|| format!("failed to read {}", path.display()))?,
+            )),
+            Source::Stdin => Box::new(BufReader::new(tokio::io::stdin())),
+            Source::Inline(_) => bail!("lines can only be read from a file or stdin"),
+        };
+        Ok(Lines {
+            reader,
+            history: repeat.then(Vec::new),
+            replayed: None,
+        })
+    }
+
+    /// Returns the next line without its line ending, lines are kept byte for byte otherwise.
+    pub async fn next(&mut self) -> Result<Option<ZBytes>> {
+        if let Some(replayed) = &mut self.replayed {
+            let history = self
+                .history
+                .as_ref()
+                .expect("only repeated lines are replayed");
+            let line = history[*replayed % history.len()].clone();
+            *replayed += 1;
+            return Ok(Some(line));
+        }
+
+        let mut line = Vec::new();
+        let read = self
+            .reader
+            .read_until(b'\n', &mut line)
+            .await
+            .context("failed to read line")?;
+        if read == 0 {
+            return match &self.history {
+                Some(history) if !history.is_empty() => {
+                    self.replayed = Some(1);
+                    Ok(Some(history[0].clone()))
+                }
+                _ => Ok(None),
+            };
+        }
+
+        if line.ends_with(b"\n") {
+            line.pop();
+            if line.ends_with(b"\r") {
+                line.pop();
+            }
+        }
+        let line = ZBytes::from(line);
+        if let Some(history) = &mut self.history {
+            history.push(line.clone());
+        }
+        Ok(Some(line))
+    }
+}
+
+/// Infers the encoding of a file from its extension.
+pub fn encoding_of(path: &Path) -> Option<Encoding> {
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    let encoding = match extension.as_str() {
+        "txt" | "text" | "log" => Encoding::TEXT_PLAIN,
+        "json" => Encoding::APPLICATION_JSON,
+        "json5" => Encoding::TEXT_JSON5,
+        "cbor" => Encoding::APPLICATION_CBOR,
+        "cdr" => Encoding::APPLICATION_CDR,
+        "yaml" | "yml" => Encoding::APPLICATION_YAML,
+        "xml" => Encoding::APPLICATION_XML,
+        "csv" => Encoding::TEXT_CSV,
+        "html" | "htm" => Encoding::TEXT_HTML,
+        "css" => Encoding::TEXT_CSS,
+        "js" => Encoding::TEXT_JAVASCRIPT,
+        "md" => Encoding::TEXT_MARKDOWN,
+        "sql" => Encoding::APPLICATION_SQL,
+        "pb" => Encoding::APPLICATION_PROTOBUF,
+        "png" => Encoding::IMAGE_PNG,
+        "jpg" | "jpeg" => Encoding::IMAGE_JPEG,
+        "gif" => Encoding::IMAGE_GIF,
+        "bmp" => Encoding::IMAGE_BMP,
+        "webp" => Encoding::IMAGE_WEBP,
+        "mp4" => Encoding::VIDEO_MP4,
+        "ogg" => Encoding::AUDIO_OGG,
+        "flac" => Encoding::AUDIO_FLAC,
+        "aac" => Encoding::AUDIO_AAC,
+        "bin" => Encoding::APPLICATION_OCTET_STREAM,
+        _ => return None,
+    };
+    Some(encoding)
+}
+
+fn detect_encoding(bytes: &[u8]) -> Encoding {
+    if str::from_utf8(bytes).is_ok() {
+        Encoding::TEXT_PLAIN
+    } else {
+        Encoding::APPLICATION_OCTET_STREAM
+    }
+}
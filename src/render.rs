@@ -0,0 +1,119 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::{Error, bail};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use zenoh::bytes::{Encoding, ZBytes};
+
+/// Binary payloads up to this size are displayed as a hex dump, bigger ones are summarized.
+const MAX_HEX_DUMP_LEN: usize = 64;
+
+/// How payloads are displayed.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Mode {
+    /// Picks one of the other modes based on the encoding and the contents of the payload.
+    #[default]
+    Auto,
+    Text,
+    Json,
+    Hex,
+    Base64,
+    Summary,
+}
+
+impl FromStr for Mode {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(Mode::Auto),
+            "text" => Ok(Mode::Text),
+            "json" => Ok(Mode::Json),
+            "hex" => Ok(Mode::Hex),
+            "base64" => Ok(Mode::Base64),
+            "summary" => Ok(Mode::Summary),
+            _ => bail!(
+                "unknown render mode: {s}, expected one of auto, text, json, hex, base64, summary"
+            ),
+        }
+    }
+}
+
+/// Renders the payload for displaying, never fails even if the payload doesn't match its encoding.
+pub fn render(payload: &ZBytes, encoding: &Encoding, mode: Mode) -> String {
+    let bytes = payload.to_bytes();
+    let mode = match mode {
+        Mode::Auto => detect(&bytes, encoding),
+        mode => mode,
+    };
+
+    match mode {
+        Mode::Auto => unreachable!("mode must be detected"),
+        Mode::Text => String::from_utf8_lossy(&bytes).into_owned(),
+        Mode::Json => match serde_json::from_slice::<serde_json::Value>(&bytes) {
+            Ok(value) => value.to_string(),
+            Err(_) => String::from_utf8_lossy(&bytes).into_owned(),
+        },
+        Mode::Hex => hex_dump(&bytes),
+        Mode::Base64 => BASE64.encode(&bytes),
+        Mode::Summary => format!("<{} bytes, {encoding}>", bytes.len()),
+    }
+}
+
+fn detect(bytes: &[u8], encoding: &Encoding) -> Mode {
+    let encoding = encoding.to_string();
+    let mime = encoding.split(';').next().unwrap_or_default();
+
+    match mime {
+        "application/json" | "text/json" | "text/json5" => Mode::Json,
+        "zenoh/string"
+        | "application/xml"
+        | "application/yaml"
+        | "application/sql"
+        | "application/x-www-form-urlencoded" => Mode::Text,
+        mime if mime.starts_with("text/") => Mode::Text,
+        // Untyped payloads are displayed as text if they look like one.
+        "" | "zenoh/bytes" if is_text(bytes) => Mode::Text,
+        _ if bytes.len() <= MAX_HEX_DUMP_LEN => Mode::Hex,
+        _ => Mode::Summary,
+    }
+}
+
+fn is_text(bytes: &[u8]) -> bool {
+    std::str::from_utf8(bytes).is_ok_and(|text| {
+        text.chars()
+            .all(|c| !c.is_control() || matches!(c, '\n' | '\r' | '\t'))
+    })
+}
+
+/// Formats bytes like `xxd`, 16 bytes per line with their offset and printable characters.
+fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        if i > 0 {
+            dump.push('\n');
+        }
+        let _ = write!(dump, "{:08x}: ", i * 16);
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(byte) => {
+                    let _ = write!(dump, "{byte:02x}");
+                }
+                None => dump.push_str("  "),
+            }
+            if j % 2 == 1 {
+                dump.push(' ');
+            }
+        }
+        dump.push(' ');
+        dump.extend(chunk.iter().map(|&byte| {
+            if byte.is_ascii_graphic() || byte == b' ' {
+                byte as char
+            } else {
+                '.'
+            }
+        }));
+    }
+    dump
+}
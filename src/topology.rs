@@ -0,0 +1,201 @@
+use std::fmt::Write;
+use std::str::FromStr;
+
+use anyhow::{Error, bail};
+use serde_json::json;
+
+use super::admin::Graph;
+use super::command::{Connection, Node};
+
+/// How `topology` prints the graph.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum Style {
+    /// Graphviz DOT, e.g. for `dot -Tsvg`.
+    #[default]
+    Dot,
+    /// A Mermaid flowchart, e.g. for Markdown documents.
+    Mermaid,
+    Json,
+}
+
+impl FromStr for Style {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "dot" => Ok(Style::Dot),
+            "mermaid" => Ok(Style::Mermaid),
+            "json" => Ok(Style::Json),
+            _ => bail!("unknown graph format: {s}, expected one of dot, mermaid, json"),
+        }
+    }
+}
+
+/// The nodes the session knows about and the links between them.
+#[derive(Default)]
+pub struct Topology {
+    nodes: Vec<Vertex>,
+    links: Vec<Link>,
+}
+
+struct Vertex {
+    zid: String,
+    whatami: String,
+    locators: Vec<String>,
+}
+
+struct Link {
+    from: String,
+    to: String,
+    weight: Option<f64>,
+}
+
+impl Topology {
+    /// Links the session to the nodes it's connected to, the routers to the nodes they have a
+    /// session with, and the routers to each other with the weights of their link state.
+    pub fn new(nodes: Vec<Node>, graphs: Vec<Graph>) -> Self {
+        let mut topology = Topology::default();
+        let local = nodes
+            .iter()
+            .find(|node| matches!(node.connection, Connection::Local))
+            .map(|node| node.zid.clone());
+        for node in nodes {
+            match (&node.connection, &local) {
+                (Connection::Local, _) => {}
+                (Connection::Direct, Some(local)) => topology.link(local, &node.zid, None),
+                (Connection::Direct, None) => {}
+                (Connection::Via(router), _) => topology.link(router, &node.zid, None),
+            }
+            topology.node(node.zid, node.whatami, node.locators);
+        }
+
+        for graph in graphs {
+            let whatami = match graph.network.as_str() {
+                "routers" => "router",
+                _ => "peer",
+            };
+            for zid in graph.nodes {
+                topology.node(zid, whatami.to_string(), Vec::new());
+            }
+            for edge in graph.edges {
+                topology.link(&edge.from, &edge.to, edge.weight);
+            }
+        }
+
+        topology
+    }
+
+    /// Adds a node, unless it's already known.
+    fn node(&mut self, zid: String, whatami: String, locators: Vec<String>) {
+        if !self.nodes.iter().any(|node| node.zid == zid) {
+            self.nodes.push(Vertex {
+                zid,
+                whatami,
+                locators,
+            });
+        }
+    }
+
+    /// Adds a link in either direction, or its weight if it's already known.
+    fn link(&mut self, from: &str, to: &str, weight: Option<f64>) {
+        let known = self.links.iter_mut().find(|link| {
+            (link.from == from && link.to == to) || (link.from == to && link.to == from)
+        });
+        match known {
+            Some(link) => link.weight = link.weight.or(weight),
+            None => self.links.push(Link {
+                from: from.to_string(),
+                to: to.to_string(),
+                weight,
+            }),
+        }
+    }
+
+    pub fn render(&self, style: Style) -> String {
+        match style {
+            Style::Dot => self.dot(),
+            Style::Mermaid => self.mermaid(),
+            Style::Json => {
+                let nodes = self
+                    .nodes
+                    .iter()
+                    .map(|node| {
+                        json!({
+                            "zid": node.zid,
+                            "whatami": node.whatami,
+                            "locators": node.locators,
+                        })
+                    })
+                    .collect::<Vec<_>>();
+                let links = self
+                    .links
+                    .iter()
+                    .map(|link| json!({ "from": link.from, "to": link.to, "weight": link.weight }))
+                    .collect::<Vec<_>>();
+                let graph = json!({ "nodes": nodes, "links": links });
+                format!(
+                    "{}\n",
+                    serde_json::to_string_pretty(&graph).expect("graph must be serializable")
+                )
+            }
+        }
+    }
+
+    /// Routers are boxes, peers are ellipses and clients are plain text.
+    fn dot(&self) -> String {
+        let mut dot = String::from("graph zenoh {\n");
+        for node in &self.nodes {
+            let shape = match node.whatami.as_str() {
+                "router" => "box",
+                "peer" => "ellipse",
+                _ => "plaintext",
+            };
+            let _ = writeln!(
+                dot,
+                "    \"{}\" [label=\"{}\\n{}\", shape={shape}];",
+                node.zid, node.zid, node.whatami
+            );
+        }
+        for link in &self.links {
+            let _ = write!(dot, "    \"{}\" -- \"{}\"", link.from, link.to);
+            if let Some(weight) = link.weight {
+                let _ = write!(dot, " [label=\"{weight}\"]");
+            }
+            dot.push_str(";\n");
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    /// Routers are rectangles, peers are rounded and clients are stadiums.
+    fn mermaid(&self) -> String {
+        let id = |zid: &str| {
+            self.nodes
+                .iter()
+                .position(|node| node.zid == zid)
+                .map_or_else(|| zid.to_string(), |i| format!("n{i}"))
+        };
+
+        let mut mermaid = String::from("graph LR\n");
+        for (i, node) in self.nodes.iter().enumerate() {
+            let label = format!("\"{}<br/>{}\"", node.zid, node.whatami);
+            let _ = match node.whatami.as_str() {
+                "router" => writeln!(mermaid, "    n{i}[{label}]"),
+                "peer" => writeln!(mermaid, "    n{i}({label})"),
+                _ => writeln!(mermaid, "    n{i}([{label}])"),
+            };
+        }
+        for link in &self.links {
+            let _ = match link.weight {
+                Some(weight) => writeln!(
+                    mermaid,
+                    "    {} ---|{weight}| {}",
+                    id(&link.from),
+                    id(&link.to)
+                ),
+                None => writeln!(mermaid, "    {} --- {}", id(&link.from), id(&link.to)),
+            };
+        }
+        mermaid
+    }
+}
@@ -1,38 +1,118 @@
 use std::borrow::Cow;
+use std::collections::HashSet;
+use std::io::{self, IsTerminal};
+use std::ops::Range;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
-use anyhow::{Result, bail};
+use anyhow::{Result, anyhow, bail};
 use colored::Colorize;
 use rustyline::{
     Changeset, Config, Context, Editor, Helper,
     completion::{Completer, Pair},
     error::ReadlineError,
-    highlight::Highlighter,
+    highlight::{CmdKind, Highlighter},
     hint::Hinter,
     history::DefaultHistory,
     line_buffer::LineBuffer,
-    validate::Validator,
+    validate::{ValidationContext, ValidationResult, Validator},
 };
+use serde_json::json;
 use tokio::signal;
 use tokio::sync::mpsc;
+use tokio::time;
+use zenoh::bytes::{Encoding, ZBytes};
+use zenoh::config::{WhatAmI, WhatAmIMatcher};
+use zenoh::key_expr::KeyExpr;
+use zenoh::qos::{CongestionControl, Priority, Reliability};
+use zenoh::query::{ConsolidationMode, QueryTarget};
+use zenoh::scouting::Hello;
 
-use super::command::{Command, KeyValue};
+use super::admin;
+use super::args::Args;
+use super::command::{
+    self, Command, GetOptions, Node, PublishOptions, Query, Replies, Sample, Schedule, TokenChange,
+};
+use super::exit::Code;
+use super::history;
+use super::keys::Keys;
+use super::output::{Format, Output, kind_name, render_payload};
+use super::payload;
+use super::render;
+use super::topology::Style;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 
-pub async fn start(commands: mpsc::Sender<Command>) -> Result<()> {
+/// The size of the samples published by `bench throughput` by default.
+const DEFAULT_BENCH_SIZE: usize = 8;
+
+/// How long `bench throughput` publishes by default.
+const DEFAULT_BENCH_DURATION: Duration = Duration::from_secs(5);
+
+/// The size of the pings by default.
+const DEFAULT_PING_SIZE: usize = 64;
+
+/// How often pings are sent by default.
+const DEFAULT_PING_INTERVAL: Duration = Duration::from_secs(1);
+
+/// How long to wait for a pong by default.
+const DEFAULT_PING_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How long `scout` waits for nodes to reply by default.
+const DEFAULT_SCOUT_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How often `pub` publishes a template if no rate or interval is given.
+const DEFAULT_PUBLISH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Printed when no router replies to an admin query.
+const NO_ADMIN_REPLIES: &str = "no routers replied, is their admin space enabled?";
+
+pub async fn start(
+    commands: mpsc::Sender<Command>,
+    format: Format,
+    mut history_path: Option<PathBuf>,
+    keys: Keys,
+) -> Result<()> {
     println!("Zenoh CLI v{VERSION}");
-    let mut rl: Editor<ZenohHelper, DefaultHistory> =
-        Editor::with_config(Config::builder().auto_add_history(true).build())?;
-    rl.set_helper(Some(ZenohHelper {}));
+    let config = Config::builder()
+        .auto_add_history(true)
+        .max_history_size(history::MAX_SIZE)?
+        .history_ignore_dups(true)?
+        .history_ignore_space(true)
+        .build();
+    let mut rl: Editor<ZenohHelper, DefaultHistory> = Editor::with_config(config)?;
+    rl.set_helper(Some(ZenohHelper {
+        keys,
+        terminal: io::stdin().is_terminal(),
+    }));
+    // The CLI stays usable without history if it can't be loaded or saved.
+    if let Some(path) = &history_path
+        && let Err(err) = history::load(rl.history_mut(), path)
+    {
+        println!("{}", format!("error: {err}").red());
+        history_path = None;
+    }
 
-    for input in rl.iter("> ") {
-        let input = match input {
+    loop {
+        let input = match rl.readline("> ") {
             Ok(input) => input,
             Err(ReadlineError::Eof | ReadlineError::Interrupted) => break,
             Err(err) => bail!("failed to read input: {err}"),
         };
+        // Saved as soon as it's read, so the other sessions see it and it's kept if the CLI is killed.
+        if let Some(path) = &history_path
+            && let Err(err) = history::append(rl.history_mut(), path)
+        {
+            println!("{}", format!("error: {err}").red());
+            history_path = None;
+        }
 
-        if let Err(err) = handle(&commands, input).await {
+        let res = match Args::parse(&input) {
+            Ok(args) if args.peek() == Some("history") => print_history(rl.history(), format, args),
+            Ok(args) => handle(&commands, format, args).await,
+            Err(err) => Err(err),
+        };
+        if let Err(err) = res {
             if err.downcast_ref::<Exit>().is_some() {
                 break;
             }
@@ -44,19 +124,28 @@ pub async fn start(commands: mpsc::Sender<Command>) -> Result<()> {
     Ok(())
 }
 
-pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<()> {
-    let mut input = input.trim().split(" ");
-    match input.next().unwrap_or_default() {
+pub async fn handle(
+    commands: &mpsc::Sender<Command>,
+    format: Format,
+    mut args: Args,
+) -> Result<()> {
+    match args.next()?.unwrap_or_default().as_str() {
         "q" | "quit" => bail!(Exit),
         "get" => {
-            let Some(selector) = input.next().map(str::to_string) else {
+            let mode = args.parsed(&["-r", "--render"])?.unwrap_or_default();
+            let verbose = args.flag(&["-v", "--verbose"]);
+            let fail_on_empty = args.flag(&["--fail-on-empty"]);
+            let options = get_options(&mut args)?;
+            let Some(selector) = args.next()? else {
                 bail!("missing selector");
             };
+            args.finish()?;
 
             let (tx, mut rx) = mpsc::channel(8);
             if let Err(err) = commands
                 .send(Command::Get {
                     selector,
+                    options,
                     reply: tx,
                 })
                 .await
@@ -64,28 +153,60 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
                 bail!("failed to send command: {err}");
             }
 
-            let mut num_replies = 0;
+            let mut output = Output::new(format).render(mode).verbose(verbose);
+            let (mut num_ok, mut num_errors) = (0, 0);
             while let Some(res) = rx.recv().await {
-                print_key_value(res?);
-                num_replies += 1;
+                let reply = res?;
+                if reply.result.is_ok() {
+                    num_ok += 1;
+                } else {
+                    num_errors += 1;
+                }
+                output.reply(reply);
             }
-            if num_replies == 0 {
-                println!("{}", "no replies received".bright_black());
+            if num_ok + num_errors == 0 {
+                if fail_on_empty {
+                    output.finish();
+                    return Err(Code::NoReplies.error("no replies received"));
+                }
+                output.status("no replies received");
+            } else {
+                output.status(&format!(
+                    "{num_ok} ok, {num_errors} {}",
+                    if num_errors == 1 { "error" } else { "errors" }
+                ));
+            }
+            output.finish();
+            if num_errors > 0 {
+                return Err(Code::ErrorReply.error(format!(
+                    "received {num_errors} error {}",
+                    if num_errors == 1 { "reply" } else { "replies" }
+                )));
             }
         }
         "put" => {
-            let Some(keyexpr) = input.next().map(str::to_string) else {
+            let encoding = args.option(&["--encoding"])?;
+            let options = publish_options(&mut args)?;
+            let Some(keyexpr) = args.next()? else {
                 bail!("missing key expression");
             };
-            let Some(payload) = input.next().map(str::to_string) else {
+            let Some(payload) = args.rest_of_line()? else {
                 bail!("missing payload");
             };
+            let source = payload::Source::new(payload);
+            if matches!(source, payload::Source::Stdin) && args.is_interactive() {
+                bail!("reading the payload from stdin is only supported in one-shot mode");
+            }
+            let (payload, inferred) = source.read()?;
+            let encoding = encoding.map_or(inferred, Encoding::from);
 
             let (tx, mut rx) = mpsc::channel(1);
             if let Err(err) = commands
                 .send(Command::Put {
                     keyexpr,
                     payload,
+                    encoding,
+                    options,
                     reply: tx,
                 })
                 .await
@@ -94,9 +215,7 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
             }
 
             match rx.recv().await {
-                Some(Ok(())) => {
-                    println!("{}", "ok".bright_black())
-                }
+                Some(Ok(())) => Output::new(format).status("ok"),
                 Some(Err(err)) => {
                     bail!(err);
                 }
@@ -106,19 +225,26 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
             }
         }
         "delete" | "del" => {
-            let Some(keyexpr) = input.next().map(str::to_string) else {
+            let options = publish_options(&mut args)?;
+            let Some(keyexpr) = args.next()? else {
                 bail!("missing key expression");
             };
+            args.finish()?;
 
             let (tx, mut rx) = mpsc::channel(1);
-            if let Err(err) = commands.send(Command::Delete { keyexpr, reply: tx }).await {
+            if let Err(err) = commands
+                .send(Command::Delete {
+                    keyexpr,
+                    options,
+                    reply: tx,
+                })
+                .await
+            {
                 bail!("failed to send command: {err}");
             }
 
             match rx.recv().await {
-                Some(Ok(())) => {
-                    println!("{}", "ok".bright_black())
-                }
+                Some(Ok(())) => Output::new(format).status("ok"),
                 Some(Err(err)) => {
                     bail!(err);
                 }
@@ -127,10 +253,105 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
                 }
             }
         }
+        "publish" | "pub" => {
+            let encoding = args.option(&["--encoding"])?;
+            let options = publish_options(&mut args)?;
+            let rate = args.parsed::<f64>(&["--rate"])?;
+            let interval = args.parsed(&["--interval"])?.map(Duration::from_millis);
+            let repeat = args.flag(&["--repeat"]);
+            let count = args.parsed(&["--count"])?;
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            let source = match args.rest_of_line()? {
+                Some(arg) => payload::Source::new(arg),
+                None => payload::Source::Stdin,
+            };
+            if matches!(source, payload::Source::Stdin) && args.is_interactive() {
+                bail!("reading lines from stdin is only supported in one-shot mode");
+            }
+
+            let encoding = match (&source, encoding) {
+                (_, Some(encoding)) => Encoding::from(encoding),
+                (payload::Source::File(path), None) => {
+                    payload::encoding_of(path).unwrap_or(Encoding::TEXT_PLAIN)
+                }
+                (_, None) => Encoding::TEXT_PLAIN,
+            };
+            let period = match (rate, interval) {
+                (Some(_), Some(_)) => bail!("--rate and --interval can't be used together"),
+                (Some(rate), None) if !(rate > 0.0 && rate.is_finite()) => {
+                    bail!("invalid value for --rate: {rate}, expected a positive number")
+                }
+                (Some(rate), None) => {
+                    // The period is rounded down to nanoseconds, and can't be zero.
+                    let period = Duration::from_secs_f64(1.0 / rate);
+                    if period.is_zero() {
+                        bail!(
+                            "invalid value for --rate: {rate}, expected at most 1000000000 per second"
+                        );
+                    }
+                    Some(period)
+                }
+                (None, Some(interval)) if interval.is_zero() => {
+                    bail!(
+                        "invalid value for --interval: 0, expected a positive number of milliseconds"
+                    )
+                }
+                (None, interval) => interval,
+            };
+            let (payloads, period) = match source {
+                payload::Source::Inline(template) => (
+                    payload::Payloads::Template(payload::Template::parse(&template)?),
+                    // Templates are published forever, so they aren't published as fast as possible.
+                    Some(period.unwrap_or(DEFAULT_PUBLISH_INTERVAL)),
+                ),
+                source => (payload::Payloads::Lines(source), period),
+            };
+
+            let (tx, mut rx) = mpsc::channel(1);
+            if let Err(err) = commands
+                .send(Command::Publish {
+                    keyexpr,
+                    payloads,
+                    encoding,
+                    options,
+                    schedule: Schedule {
+                        period,
+                        repeat,
+                        count,
+                    },
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            let output = Output::new(format);
+            tokio::select! {
+                res = rx.recv() => match res {
+                    Some(res) => {
+                        let published = res?;
+                        output.status(&format!(
+                            "published {published} {}",
+                            if published == 1 { "sample" } else { "samples" }
+                        ));
+                    }
+                    None => {
+                        bail!("failed to publish");
+                    }
+                },
+                _ = signal::ctrl_c() => {}
+            }
+        }
         "subscribe" | "sub" => {
-            let Some(keyexpr) = input.next().map(str::to_string) else {
+            let mode = args.parsed(&["-r", "--render"])?.unwrap_or_default();
+            let verbose = args.flag(&["-v", "--verbose"]);
+            let Some(keyexpr) = args.next()? else {
                 bail!("missing key expression");
             };
+            args.finish()?;
 
             let (tx, mut rx) = mpsc::channel(8);
             if let Err(err) = commands
@@ -140,11 +361,74 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
                 bail!("failed to send command: {err}");
             }
 
+            let mut output = Output::new(format).render(mode).verbose(verbose);
+            loop {
+                tokio::select! {
+                    res = rx.recv() => match res {
+                        Some(res) => {
+                            output.sample(res?);
+                        }
+                        None => {
+                            break;
+                        }
+                    },
+                    _ = signal::ctrl_c() => {
+                        break;
+                    }
+                }
+            }
+            output.finish();
+        }
+        "ping" => {
+            let count = args.parsed(&["-c", "--count"])?;
+            let size = args.parsed(&["-s", "--size"])?.unwrap_or(DEFAULT_PING_SIZE);
+            if size < command::SEQUENCE_LEN {
+                bail!(
+                    "invalid value for --size: {size}, expected at least {} bytes for the sequence number",
+                    command::SEQUENCE_LEN
+                );
+            }
+            let interval = args
+                .parsed(&["-i", "--interval"])?
+                .map_or(DEFAULT_PING_INTERVAL, Duration::from_millis);
+            if interval.is_zero() {
+                bail!(
+                    "invalid value for --interval: 0, expected a positive number of milliseconds"
+                );
+            }
+            let timeout = args
+                .parsed(&["-t", "--timeout"])?
+                .map_or(DEFAULT_PING_TIMEOUT, Duration::from_millis);
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(8);
+            if let Err(err) = commands
+                .send(Command::Ping {
+                    keyexpr: keyexpr.clone(),
+                    count,
+                    size,
+                    interval,
+                    timeout,
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            let mut output = Output::new(format);
+            let (mut transmitted, mut rtts) = (0, Vec::new());
             loop {
                 tokio::select! {
                     res = rx.recv() => match res {
                         Some(res) => {
-                            print_key_value(res?);
+                            let pong = res?;
+                            output.pong(&keyexpr, &pong);
+                            transmitted += 1;
+                            rtts.extend(pong.rtt);
                         }
                         None => {
                             break;
@@ -155,8 +439,105 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
                     }
                 }
             }
+            let received = rtts.len();
+            output.ping_stats(&keyexpr, transmitted, rtts);
+            output.finish();
+            if transmitted > 0 && received == 0 {
+                return Err(Code::NoReplies.error("no pongs received"));
+            }
         }
+        "pong" => {
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(1);
+            if let Err(err) = commands
+                .send(Command::Pong {
+                    keyexpr: keyexpr.clone(),
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            match rx.recv().await {
+                Some(Ok(())) => {
+                    Output::new(format).status(&format!("replying to pings on {keyexpr}"))
+                }
+                Some(Err(err)) => {
+                    bail!(err);
+                }
+                None => {
+                    bail!("failed to reply to pings");
+                }
+            }
+
+            // Dropping the receiver stops replying.
+            signal::ctrl_c().await?;
+        }
+        "queryable" => {
+            let table = args.flag(&["-t", "--table"]);
+            let mode = args.parsed(&["-r", "--render"])?.unwrap_or_default();
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            let replies = if table {
+                let entries = args
+                    .rest()?
+                    .into_iter()
+                    .map(|entry| match entry.split_once('=') {
+                        Some((key, value)) => Ok((key.to_string(), value.to_string())),
+                        None => bail!("invalid table entry: {entry}, expected <key>=<value>"),
+                    })
+                    .collect::<Result<Vec<_>>>()?;
+                if entries.is_empty() {
+                    bail!("missing table entries");
+                }
+                Replies::Table(entries)
+            } else {
+                let Some(payload) = args.rest_of_line()? else {
+                    bail!("missing reply payload");
+                };
+                Replies::Payload(payload)
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(8);
+            if let Err(err) = commands
+                .send(Command::Queryable {
+                    keyexpr,
+                    replies,
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            loop {
+                tokio::select! {
+                    res = rx.recv() => match res {
+                        Some(res) => {
+                            print_query(res?, mode);
+                        }
+                        None => {
+                            break;
+                        }
+                    },
+                    _ = signal::ctrl_c() => {
+                        break;
+                    }
+                }
+            }
+        }
+        "liveliness" | "live" => liveliness(commands, format, args).await?,
+        "bench" => bench(commands, format, args).await?,
+        "admin" => admin(commands, format, args).await?,
         "zid" => {
+            args.finish()?;
             let (tx, mut rx) = mpsc::channel(1);
             if let Err(err) = commands.send(Command::Zid { reply: tx }).await {
                 bail!("failed to send command: {err}");
@@ -164,7 +545,9 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
 
             match rx.recv().await {
                 Some(Ok(zid)) => {
-                    println!("{zid}")
+                    let mut output = Output::new(format);
+                    output.zid(zid);
+                    output.finish();
                 }
                 Some(Err(err)) => {
                     bail!(err);
@@ -175,23 +558,558 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
             }
         }
         "peers" => {
-            let (tx, mut rx) = mpsc::channel(8);
+            args.finish()?;
+            let (tx, rx) = mpsc::channel(8);
             if let Err(err) = commands.send(Command::Peers { reply: tx }).await {
                 bail!("failed to send command: {err}");
             }
+            print_nodes(rx, format, false, "no peers found").await?;
+        }
+        "routers" => {
+            args.finish()?;
+            let (tx, rx) = mpsc::channel(8);
+            if let Err(err) = commands.send(Command::Routers { reply: tx }).await {
+                bail!("failed to send command: {err}");
+            }
+            print_nodes(rx, format, false, "no routers found").await?;
+        }
+        "scout" => {
+            let (what, timeout) = scout_options(args)?;
+            let (tx, rx) = mpsc::channel(8);
+            if let Err(err) = commands.send(Command::Scout { what, reply: tx }).await {
+                bail!("failed to send command: {err}");
+            }
+            print_hellos(rx, format, timeout).await?;
+        }
+        "nodes" => {
+            args.finish()?;
+            let (tx, rx) = mpsc::channel(8);
+            if let Err(err) = commands.send(Command::Nodes { reply: tx }).await {
+                bail!("failed to send command: {err}");
+            }
+            print_nodes(rx, format, true, "no nodes found").await?;
+        }
+        "topology" => {
+            let style = match args.next()? {
+                Some(style) => style.parse::<Style>()?,
+                None => Style::default(),
+            };
+            args.finish()?;
+            let (tx, mut rx) = mpsc::channel(1);
+            if let Err(err) = commands.send(Command::Topology { reply: tx }).await {
+                bail!("failed to send command: {err}");
+            }
 
-            let mut num_replies = 0;
-            while let Some(res) = rx.recv().await {
-                println!("{}", res?);
-                num_replies += 1;
+            match rx.recv().await {
+                Some(Ok(topology)) => print!("{}", topology.render(style)),
+                Some(Err(err)) => bail!(err),
+                None => bail!("failed to get topology"),
             }
-            if num_replies == 0 {
-                println!("{}", "no peers found".bright_black());
+        }
+        cmd => {
+            if cmd.is_empty() {
+                bail!("missing command");
             }
+            bail!("unknown command: {cmd}");
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs `history` on the saved history, without opening a session.
+pub fn history(path: Option<&Path>, format: Format, args: Args) -> Result<()> {
+    let mut entries = DefaultHistory::new();
+    if let Some(path) = path {
+        history::load(&mut entries, path)?;
+    }
+    print_history(&entries, format, args)
+}
+
+/// Prints the lines of the history that contain the given text, or the last ones with `-n`.
+fn print_history(entries: &DefaultHistory, format: Format, mut args: Args) -> Result<()> {
+    // Skips the name of the command.
+    args.next()?;
+    let count = args.parsed::<usize>(&["-n", "--count"])?;
+    let text = args.rest()?.join(" ");
+    args.finish()?;
+
+    let mut lines = entries
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| line.contains(&text))
+        .collect::<Vec<_>>();
+    if let Some(count) = count {
+        lines.drain(..lines.len().saturating_sub(count));
+    }
+
+    let mut output = Output::new(format);
+    for (i, line) in &lines {
+        output.history(i + 1, line);
+    }
+    if lines.is_empty() {
+        output.status(if text.is_empty() {
+            "history is empty"
+        } else {
+            "no matching lines"
+        });
+    }
+    output.finish();
+    Ok(())
+}
+
+/// Runs `scout` without opening a session, to find nodes before connecting to them.
+pub async fn scout(config: zenoh::Config, format: Format, mut args: Args) -> Result<()> {
+    // Skips the name of the command.
+    args.next()?;
+    let (what, timeout) = scout_options(args)?;
+
+    let (tx, rx) = mpsc::channel(8);
+    let scouting = tokio::spawn(async move {
+        if let Err(err) = command::scout(config, what, &tx).await {
+            let _ = tx.send(Err(err)).await;
+        }
+    });
+    let res = print_hellos(rx, format, timeout).await;
+    scouting.abort();
+    res
+}
+
+fn scout_options(mut args: Args) -> Result<(WhatAmIMatcher, Duration)> {
+    let what = args
+        .parsed_with(&["--what"], |what| {
+            what.replace(',', "|").parse::<WhatAmIMatcher>().map_err(|()| {
+                anyhow!("unknown node kind: {what}, expected peer, router or client, separated by commas")
+            })
+        })?
+        .unwrap_or(WhatAmI::Router | WhatAmI::Peer);
+    let timeout = args
+        .parsed(&["-t", "--timeout"])?
+        .map_or(DEFAULT_SCOUT_TIMEOUT, Duration::from_millis);
+    args.finish()?;
+    Ok((what, timeout))
+}
+
+/// Prints each node the first time it replies to scouting, until the timeout.
+async fn print_hellos(
+    mut rx: mpsc::Receiver<Result<Hello>>,
+    format: Format,
+    timeout: Duration,
+) -> Result<()> {
+    let mut output = Output::new(format);
+    let mut found = HashSet::new();
+    let deadline = time::sleep(timeout);
+    tokio::pin!(deadline);
+    loop {
+        tokio::select! {
+            res = rx.recv() => match res {
+                Some(res) => {
+                    // Nodes reply to every scout message, which is sent periodically.
+                    let hello = res?;
+                    if found.insert(hello.zid()) {
+                        output.hello(&hello);
+                    }
+                }
+                None => {
+                    break;
+                }
+            },
+            _ = &mut deadline => {
+                break;
+            }
+            _ = signal::ctrl_c() => {
+                break;
+            }
+        }
+    }
+    match found.len() {
+        0 => output.status("no nodes found"),
+        1 => output.status("found 1 node"),
+        n => output.status(&format!("found {n} nodes")),
+    }
+    output.finish();
+    Ok(())
+}
+
+/// Prints the nodes once they're all received, to align them in a table.
+async fn print_nodes(
+    mut rx: mpsc::Receiver<Result<Node>>,
+    format: Format,
+    connection: bool,
+    empty: &str,
+) -> Result<()> {
+    let mut nodes = Vec::new();
+    while let Some(res) = rx.recv().await {
+        nodes.push(res?);
+    }
+
+    let mut output = Output::new(format);
+    if nodes.is_empty() {
+        output.status(empty);
+    } else {
+        output.nodes(nodes, connection);
+    }
+    output.finish();
+    Ok(())
+}
+
+fn get_options(args: &mut Args) -> Result<GetOptions> {
+    let target = args.parsed_with(&["--target"], |target| match target {
+        "best-matching" => Ok(QueryTarget::BestMatching),
+        "all" => Ok(QueryTarget::All),
+        "all-complete" => Ok(QueryTarget::AllComplete),
+        _ => bail!("unknown target: {target}, expected one of best-matching, all, all-complete"),
+    })?;
+    let consolidation = args.parsed_with(&["--consolidation"], |consolidation| {
+        match consolidation {
+            "auto" => Ok(ConsolidationMode::Auto),
+            "none" => Ok(ConsolidationMode::None),
+            "monotonic" => Ok(ConsolidationMode::Monotonic),
+            "latest" => Ok(ConsolidationMode::Latest),
+            _ => bail!(
+                "unknown consolidation: {consolidation}, expected one of auto, none, monotonic, latest"
+            ),
         }
+    })?;
+    let timeout = args.parsed(&["--timeout"])?.map(Duration::from_millis);
+    let payload = args.option(&["--payload"])?;
+    let encoding = args.option(&["--encoding"])?;
+    let attachment = args.option(&["--attachment"])?;
+
+    let payload = match (payload, encoding) {
+        (Some(payload), encoding) => Some((
+            ZBytes::from(payload),
+            encoding.map_or(Encoding::TEXT_PLAIN, Encoding::from),
+        )),
+        (None, Some(_)) => bail!("--encoding requires --payload"),
+        (None, None) => None,
+    };
+
+    Ok(GetOptions {
+        target: target.unwrap_or_default(),
+        consolidation: consolidation.unwrap_or_default(),
+        timeout,
+        payload,
+        attachment: attachment.map(ZBytes::from),
+    })
+}
+
+fn publish_options(args: &mut Args) -> Result<PublishOptions> {
+    let priority = args.parsed_with(&["--priority"], |priority| match priority {
+        "real-time" => Ok(Priority::RealTime),
+        "interactive-high" => Ok(Priority::InteractiveHigh),
+        "interactive-low" => Ok(Priority::InteractiveLow),
+        "data-high" => Ok(Priority::DataHigh),
+        "data" => Ok(Priority::Data),
+        "data-low" => Ok(Priority::DataLow),
+        "background" => Ok(Priority::Background),
+        _ => bail!(
+            "unknown priority: {priority}, expected one of real-time, interactive-high, interactive-low, data-high, data, data-low, background"
+        ),
+    })?;
+    let congestion_control =
+        args.parsed_with(
+            &["--congestion-control"],
+            |congestion_control| match congestion_control {
+                "block" => Ok(CongestionControl::Block),
+                "drop" => Ok(CongestionControl::Drop),
+                "block-first" => Ok(CongestionControl::BlockFirst),
+                _ => bail!(
+                    "unknown congestion control: {congestion_control}, expected one of block, drop, block-first"
+                ),
+            },
+        )?;
+    let reliability = args.parsed_with(&["--reliability"], |reliability| match reliability {
+        "reliable" => Ok(Reliability::Reliable),
+        "best-effort" => Ok(Reliability::BestEffort),
+        _ => bail!("unknown reliability: {reliability}, expected one of reliable, best-effort"),
+    })?;
+    let express = args.flag(&["--express"]);
+    let timestamp = args.flag(&["--timestamp"]);
+    let attachment = args.option(&["--attachment"])?;
+
+    Ok(PublishOptions {
+        priority: priority.unwrap_or_default(),
+        congestion_control: congestion_control.unwrap_or_default(),
+        express,
+        reliability: reliability.unwrap_or_default(),
+        attachment: attachment.map(ZBytes::from),
+        timestamp,
+    })
+}
+
+async fn bench(commands: &mpsc::Sender<Command>, format: Format, mut args: Args) -> Result<()> {
+    match args.next()?.unwrap_or_default().as_str() {
+        "throughput" => {
+            // Both sides run in the session unless only one of them is asked for.
+            let (publish, subscribe) =
+                match (args.flag(&["--publish"]), args.flag(&["--subscribe"])) {
+                    (false, false) => (true, true),
+                    sides => sides,
+                };
+            let size = args
+                .parsed(&["-s", "--size"])?
+                .unwrap_or(DEFAULT_BENCH_SIZE);
+            if size < command::SEQUENCE_LEN {
+                bail!(
+                    "invalid value for --size: {size}, expected at least {} bytes for the sequence number",
+                    command::SEQUENCE_LEN
+                );
+            }
+            let duration = args
+                .parsed_with(&["-d", "--duration"], |duration| {
+                    let seconds = duration.parse::<f64>()?;
+                    Ok(Duration::try_from_secs_f64(seconds)?)
+                })?
+                .unwrap_or(DEFAULT_BENCH_DURATION);
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(2);
+            if let Err(err) = commands
+                .send(Command::BenchThroughput {
+                    keyexpr,
+                    publish,
+                    subscribe,
+                    size,
+                    duration,
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            let mut output = Output::new(format);
+            loop {
+                tokio::select! {
+                    res = rx.recv() => match res {
+                        Some(res) => {
+                            output.throughput(res?);
+                        }
+                        None => {
+                            break;
+                        }
+                    },
+                    _ = signal::ctrl_c() => {
+                        break;
+                    }
+                }
+            }
+            output.finish();
+        }
+        "" => bail!("missing benchmark, expected throughput"),
+        bench => bail!("unknown benchmark: {bench}, expected throughput"),
+    }
+
+    Ok(())
+}
+
+async fn admin(commands: &mpsc::Sender<Command>, format: Format, mut args: Args) -> Result<()> {
+    let cmd = args.next()?.unwrap_or_default();
+    args.finish()?;
+
+    let mut output = Output::new(format);
+    match cmd.as_str() {
         "routers" => {
+            let routers = admin_routers(commands).await?;
+            if routers.is_empty() {
+                output.status(NO_ADMIN_REPLIES);
+            } else {
+                let rows = routers
+                    .iter()
+                    .map(|router| {
+                        vec![
+                            json!(router.zid),
+                            json!(router.short_version()),
+                            json!(router.locators),
+                            json!(router.sessions.len()),
+                        ]
+                    })
+                    .collect();
+                output.table(&["zid", "version", "locators", "sessions"], rows);
+            }
+        }
+        "sessions" => {
+            let routers = admin_routers(commands).await?;
+            let rows = routers
+                .iter()
+                .flat_map(|router| {
+                    router.sessions.iter().map(|session| {
+                        let links = session
+                            .links
+                            .iter()
+                            .map(|link| format!("{} -> {}", link.src, link.dst))
+                            .collect::<Vec<_>>();
+                        vec![
+                            json!(router.zid),
+                            json!(session.peer),
+                            json!(session.whatami),
+                            json!(links),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if routers.is_empty() {
+                output.status(NO_ADMIN_REPLIES);
+            } else if rows.is_empty() {
+                output.status("no sessions found");
+            } else {
+                output.table(&["router", "peer", "whatami", "links"], rows);
+            }
+        }
+        "plugins" => {
+            let routers = admin_routers(commands).await?;
+            let rows = routers
+                .iter()
+                .flat_map(|router| {
+                    router.plugins.iter().flatten().map(|(id, plugin)| {
+                        vec![
+                            json!(router.zid),
+                            json!(id),
+                            json!(plugin.name),
+                            json!(plugin.path),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if routers.is_empty() {
+                output.status(NO_ADMIN_REPLIES);
+            } else if rows.is_empty() {
+                output.status("no plugins found");
+            } else {
+                output.table(&["router", "id", "name", "path"], rows);
+            }
+        }
+        "linkstate" => {
+            let mut graphs = admin_get(commands, admin::LINKSTATE)
+                .await?
+                .into_iter()
+                .map(|sample| admin::Graph::parse(&sample.keyexpr, &sample.payload))
+                .collect::<Result<Vec<_>>>()?;
+            graphs.sort_by(|a, b| (&a.zid, &a.network).cmp(&(&b.zid, &b.network)));
+            let rows = graphs
+                .iter()
+                .flat_map(|graph| {
+                    graph.edges.iter().map(|edge| {
+                        vec![
+                            json!(graph.zid),
+                            json!(graph.network),
+                            json!(edge.from),
+                            json!(edge.to),
+                            json!(edge.weight),
+                        ]
+                    })
+                })
+                .collect::<Vec<_>>();
+            if graphs.is_empty() {
+                output.status(NO_ADMIN_REPLIES);
+            } else if rows.is_empty() {
+                output.status("no links found");
+            } else {
+                output.table(&["router", "network", "from", "to", "weight"], rows);
+            }
+        }
+        "" => bail!("missing admin command, expected one of routers, sessions, plugins, linkstate"),
+        cmd => bail!(
+            "unknown admin command: {cmd}, expected one of routers, sessions, plugins, linkstate"
+        ),
+    }
+    output.finish();
+
+    Ok(())
+}
+
+/// Queries what every router reports about itself, sorted by ZID.
+async fn admin_routers(commands: &mpsc::Sender<Command>) -> Result<Vec<admin::Router>> {
+    let mut routers = admin_get(commands, admin::ROUTERS)
+        .await?
+        .into_iter()
+        .map(|sample| admin::parse::<admin::Router>(&sample.keyexpr, &sample.payload))
+        .collect::<Result<Vec<_>>>()?;
+    routers.sort_by(|a, b| a.zid.cmp(&b.zid));
+    for router in &mut routers {
+        router.sessions.sort_by(|a, b| a.peer.cmp(&b.peer));
+    }
+    Ok(routers)
+}
+
+/// Queries the admin space of every router that replies.
+async fn admin_get(commands: &mpsc::Sender<Command>, selector: &str) -> Result<Vec<Sample>> {
+    let (tx, mut rx) = mpsc::channel(8);
+    if let Err(err) = commands
+        .send(Command::Get {
+            selector: selector.to_string(),
+            options: GetOptions {
+                target: QueryTarget::All,
+                ..Default::default()
+            },
+            reply: tx,
+        })
+        .await
+    {
+        bail!("failed to send command: {err}");
+    }
+
+    let mut samples = Vec::new();
+    while let Some(res) = rx.recv().await {
+        match res?.result {
+            Ok(sample) => samples.push(sample),
+            Err(err) => bail!(
+                "failed to query {selector}: {}",
+                render::render(&err.payload, &err.encoding, render::Mode::Auto)
+            ),
+        }
+    }
+    Ok(samples)
+}
+
+async fn liveliness(
+    commands: &mpsc::Sender<Command>,
+    format: Format,
+    mut args: Args,
+) -> Result<()> {
+    match args.next()?.unwrap_or_default().as_str() {
+        "declare" | "token" => {
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(1);
+            if let Err(err) = commands
+                .send(Command::LivelinessToken { keyexpr, reply: tx })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            match rx.recv().await {
+                Some(Ok(())) => Output::new(format).status("ok"),
+                Some(Err(err)) => {
+                    bail!(err);
+                }
+                None => {
+                    bail!("failed to declare liveliness token");
+                }
+            }
+
+            // Dropping the receiver undeclares the token.
+            signal::ctrl_c().await?;
+        }
+        "get" => {
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
             let (tx, mut rx) = mpsc::channel(8);
-            if let Err(err) = commands.send(Command::Routers { reply: tx }).await {
+            if let Err(err) = commands
+                .send(Command::LivelinessGet { keyexpr, reply: tx })
+                .await
+            {
                 bail!("failed to send command: {err}");
             }
 
@@ -201,60 +1119,307 @@ pub async fn handle(commands: &mpsc::Sender<Command>, input: String) -> Result<(
                 num_replies += 1;
             }
             if num_replies == 0 {
-                println!("{}", "no routers found".bright_black());
+                Output::new(format).status("no alive tokens found");
+            }
+        }
+        "subscribe" | "sub" => {
+            let history = args.flag(&["--history"]);
+            let Some(keyexpr) = args.next()? else {
+                bail!("missing key expression");
+            };
+            args.finish()?;
+
+            let (tx, mut rx) = mpsc::channel(8);
+            if let Err(err) = commands
+                .send(Command::LivelinessSubscribe {
+                    keyexpr,
+                    history,
+                    reply: tx,
+                })
+                .await
+            {
+                bail!("failed to send command: {err}");
+            }
+
+            loop {
+                tokio::select! {
+                    res = rx.recv() => match res {
+                        Some(res) => {
+                            print_token_change(res?);
+                        }
+                        None => {
+                            break;
+                        }
+                    },
+                    _ = signal::ctrl_c() => {
+                        break;
+                    }
+                }
             }
         }
         cmd => {
             if cmd.is_empty() {
-                bail!("missing command");
+                bail!("missing liveliness command");
             }
-            bail!("unknown command: {cmd}");
+            bail!("unknown liveliness command: {cmd}");
         }
     }
 
     Ok(())
 }
 
-struct ZenohHelper;
+struct ZenohHelper {
+    /// The keys seen so far, to complete the arguments of the commands taking a key expression.
+    keys: Keys,
+    /// Whether the lines are typed in a terminal rather than piped.
+    terminal: bool,
+}
 
 impl ZenohHelper {
-    const COMMANDS: [&str; 8] = [
+    const COMMANDS: [&str; 19] = [
         "quit",
         "get",
         "put",
         "delete",
+        "publish",
         "subscribe",
+        "queryable",
+        "liveliness",
+        "bench",
+        "ping",
+        "pong",
+        "admin",
         "zid",
         "peers",
         "routers",
+        "nodes",
+        "scout",
+        "topology",
+        "history",
     ];
+
+    /// The arguments of a command, hinted as they're typed. The `<keyexpr>` or `<selector>`
+    /// argument is validated and completed with the keys seen so far.
+    fn usage(cmd: &str) -> &'static [&'static str] {
+        match cmd {
+            "get" => &[
+                "<selector>",
+                "[--target ..]",
+                "[--timeout ..]",
+                "[--payload ..]",
+            ],
+            "put" => &["<keyexpr>", "<payload>", "[--encoding ..]"],
+            "delete" | "del" => &["<keyexpr>"],
+            "publish" | "pub" => &["<keyexpr>", "<template>", "[--rate ..]", "[--count ..]"],
+            "subscribe" | "sub" => &["<keyexpr>", "[--render ..]", "[--verbose]"],
+            "queryable" => &["<keyexpr>", "<payload>", "[--table]"],
+            "liveliness" | "live" => &["<declare|get|sub>", "<keyexpr>"],
+            "bench" => &["throughput", "<keyexpr>", "[--size ..]", "[--duration ..]"],
+            "ping" => &["<keyexpr>", "[--count ..]", "[--interval ..]"],
+            "pong" => &["<keyexpr>"],
+            "admin" => &["<routers|sessions|plugins|linkstate>"],
+            "scout" => &["[--what ..]", "[--timeout ..]"],
+            "topology" => &["[dot|mermaid|json]"],
+            "history" => &["[text]", "[-n ..]"],
+            _ => &[],
+        }
+    }
+
+    /// The options of a command taking a value, the word after them isn't a positional
+    /// argument. `-t` takes one for `ping` and `scout` but not for `queryable`.
+    fn value_options(cmd: &str) -> &'static [&'static str] {
+        match cmd {
+            "get" => &[
+                "-r",
+                "--render",
+                "--target",
+                "--consolidation",
+                "--timeout",
+                "--payload",
+                "--encoding",
+                "--attachment",
+            ],
+            "put" => &[
+                "--encoding",
+                "--priority",
+                "--congestion-control",
+                "--reliability",
+                "--attachment",
+            ],
+            "publish" | "pub" => &[
+                "--encoding",
+                "--priority",
+                "--congestion-control",
+                "--reliability",
+                "--attachment",
+                "--rate",
+                "--interval",
+                "--count",
+            ],
+            "delete" | "del" => &[
+                "--priority",
+                "--congestion-control",
+                "--reliability",
+                "--attachment",
+            ],
+            "subscribe" | "sub" | "queryable" => &["-r", "--render"],
+            "bench" => &["-s", "--size", "-d", "--duration"],
+            "ping" => &[
+                "-c",
+                "--count",
+                "-s",
+                "--size",
+                "-i",
+                "--interval",
+                "-t",
+                "--timeout",
+            ],
+            "scout" => &["--what", "-t", "--timeout"],
+            "history" => &["-n", "--count"],
+            _ => &[],
+        }
+    }
+}
+
+/// A line being typed, split into arguments like the command does to hint, highlight and
+/// validate it.
+#[derive(Default)]
+struct Line {
+    cmd: Word,
+    /// The positional arguments after the command.
+    positionals: Vec<Word>,
+    /// The names of the options given, e.g. `--encoding`.
+    options: Vec<String>,
+    /// Whether the last option is still waiting for its value.
+    missing_value: bool,
+}
+
+/// An argument with where it's written in the line, including its quotes.
+#[derive(Default)]
+struct Word {
+    value: String,
+    span: Range<usize>,
+}
+
+impl Line {
+    /// Fails while a quote isn't closed, like the command would.
+    fn parse(line: &str) -> Result<Self> {
+        let args = Args::parse(line)?;
+        let mut words = args.spans();
+        let mut parsed = Line::default();
+        if let Some((span, cmd, _)) = words.next() {
+            parsed.cmd = Word {
+                value: cmd.to_string(),
+                span,
+            };
+        }
+
+        let value_options = ZenohHelper::value_options(&parsed.cmd.value);
+        while let Some((span, value, option)) = words.next() {
+            match value.split_once('=') {
+                _ if !option => parsed.positionals.push(Word {
+                    value: value.to_string(),
+                    span,
+                }),
+                Some((name, _)) if value.starts_with("--") => parsed.options.push(name.to_string()),
+                _ => {
+                    parsed.options.push(value.to_string());
+                    if value_options.contains(&value) {
+                        parsed.missing_value = words.next().is_none();
+                    }
+                }
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    /// Which positional argument is the key expression of the command, if it takes one.
+    fn keyexpr_index(&self) -> Option<usize> {
+        ZenohHelper::usage(&self.cmd.value)
+            .iter()
+            .filter(|part| !part.starts_with("[-"))
+            .position(|part| matches!(*part, "<keyexpr>" | "<selector>"))
+    }
+
+    /// The key expression argument without the parameters of a selector, with where it's
+    /// written. That's the whole argument if it's quoted or escaped.
+    fn keyexpr(&self) -> Option<(Range<usize>, &str)> {
+        let arg = self.positionals.get(self.keyexpr_index()?)?;
+        let keyexpr = match ZenohHelper::usage(&self.cmd.value).contains(&"<selector>") {
+            true => arg
+                .value
+                .split_once('?')
+                .map_or(arg.value.as_str(), |(keyexpr, _)| keyexpr),
+            false => &arg.value,
+        };
+        let span = match arg.span.len() == arg.value.len() {
+            true => arg.span.start..arg.span.start + keyexpr.len(),
+            false => arg.span.clone(),
+        };
+        Some((span, keyexpr))
+    }
+
+    /// Where the key expression argument is written and why it's invalid, if it is.
+    fn invalid_keyexpr(&self) -> Option<(Range<usize>, zenoh::Error)> {
+        let (span, keyexpr) = self.keyexpr()?;
+        let err = KeyExpr::try_from(keyexpr).err()?;
+        Some((span, err))
+    }
 }
 
 impl Helper for ZenohHelper {}
 
-impl Validator for ZenohHelper {}
+impl Validator for ZenohHelper {
+    fn validate(&self, ctx: &mut ValidationContext) -> Result<ValidationResult, ReadlineError> {
+        // Piped lines aren't validated, rustyline would join an invalid one with the next.
+        if !self.terminal {
+            return Ok(ValidationResult::Valid(None));
+        }
+
+        let err = match Line::parse(ctx.input()) {
+            Ok(parsed) => match parsed.invalid_keyexpr() {
+                Some((_, err)) => err.to_string(),
+                None => return Ok(ValidationResult::Valid(None)),
+            },
+            Err(err) => err.to_string(),
+        };
+        Ok(ValidationResult::Invalid(Some(format!(
+            "\n{}",
+            format!("error: {err}").red()
+        ))))
+    }
+}
 
 impl Highlighter for ZenohHelper {
     fn highlight<'l>(&self, line: &'l str, _: usize) -> Cow<'l, str> {
-        if line.is_empty() {
+        let Ok(parsed) = Line::parse(line) else {
             return Cow::Borrowed(line);
-        }
-
-        let (cmd, args) = match line.split_once(' ') {
-            Some((cmd, args)) => (cmd, args),
-            None => (line, ""),
         };
-
-        if !ZenohHelper::COMMANDS.contains(&cmd) {
+        if !ZenohHelper::COMMANDS.contains(&parsed.cmd.value.as_str()) {
             return Cow::Borrowed(line);
         }
 
-        let new_line = format!("{} {}", cmd.bold(), args);
-        if args.is_empty() {
-            Cow::Owned(new_line.trim().to_string())
-        } else {
-            Cow::Owned(new_line)
+        // The command is bold, and the key expression red while it's invalid.
+        let cmd = &parsed.cmd.span;
+        let mut highlighted = format!("{}{}", &line[..cmd.start], line[cmd.clone()].bold());
+        match parsed.invalid_keyexpr() {
+            Some((span, _)) => {
+                highlighted.push_str(&format!(
+                    "{}{}{}",
+                    &line[cmd.end..span.start],
+                    line[span.clone()].red(),
+                    &line[span.end..]
+                ));
+            }
+            None => highlighted.push_str(&line[cmd.end..]),
         }
+        Cow::Owned(highlighted)
+    }
+
+    fn highlight_char(&self, _: &str, _: usize, kind: CmdKind) -> bool {
+        // Moving the cursor doesn't change the highlighting, typing does.
+        kind == CmdKind::Other
     }
 
     fn highlight_prompt<'b, 's: 'b, 'p: 'b>(&'s self, prompt: &'p str, _: bool) -> Cow<'b, str> {
@@ -269,15 +1434,43 @@ impl Highlighter for ZenohHelper {
 impl Hinter for ZenohHelper {
     type Hint = String;
 
-    fn hint(&self, line: &str, _: usize, _: &Context<'_>) -> Option<Self::Hint> {
+    fn hint(&self, line: &str, pos: usize, _: &Context<'_>) -> Option<Self::Hint> {
         if line.is_empty() {
             return None;
         }
+        if !line.contains(char::is_whitespace) {
+            return ZenohHelper::COMMANDS
+                .iter()
+                .find_map(|cmd| cmd.strip_prefix(line))
+                .map(str::to_string);
+        }
 
-        ZenohHelper::COMMANDS
+        // The usage is hinted between arguments, without the ones already given, unless an
+        // option is waiting for its value.
+        if pos < line.len() || !line.ends_with(char::is_whitespace) {
+            return None;
+        }
+        let parsed = Line::parse(line)
+            .ok()
+            .filter(|parsed| !parsed.missing_value)?;
+        let mut given = parsed.positionals.len();
+        let hint = ZenohHelper::usage(&parsed.cmd.value)
             .iter()
-            .find_map(|cmd| cmd.strip_prefix(line))
-            .map(str::to_string)
+            .filter(|part| match part.strip_prefix('[') {
+                Some(option) if option.starts_with('-') => {
+                    let name = option.trim_end_matches(" ..]").trim_end_matches(']');
+                    !parsed.options.iter().any(|given| given == name)
+                }
+                _ if given > 0 => {
+                    given -= 1;
+                    false
+                }
+                _ => true,
+            })
+            .copied()
+            .collect::<Vec<_>>()
+            .join(" ");
+        (!hint.is_empty()).then_some(hint)
     }
 }
 
@@ -291,6 +1484,35 @@ impl Completer for ZenohHelper {
         _: &Context<'_>,
     ) -> Result<(usize, Vec<Self::Candidate>), ReadlineError> {
         let curr = &line[0..pos];
+        let start = curr
+            .char_indices()
+            .rfind(|(_, c)| c.is_whitespace())
+            .map_or(0, |(i, c)| i + c.len_utf8());
+        if start > 0 {
+            // Only the key expression is completed, once the arguments before it are given.
+            let Ok(parsed) = Line::parse(curr) else {
+                return Ok((pos, Vec::new()));
+            };
+            let word = &curr[start..];
+            let index = match parsed.positionals.last() {
+                Some(last) if last.span.start == start => parsed.positionals.len() - 1,
+                _ => parsed.positionals.len(),
+            };
+            if word.starts_with('-') || parsed.keyexpr_index() != Some(index) {
+                return Ok((pos, Vec::new()));
+            }
+            let candidates = self
+                .keys
+                .complete(word)
+                .into_iter()
+                .map(|key| Pair {
+                    display: key.clone(),
+                    replacement: key,
+                })
+                .collect();
+            return Ok((start, candidates));
+        }
+
         let candidates = ZenohHelper::COMMANDS
             .iter()
             .filter(|cmd| cmd.starts_with(curr))
@@ -307,17 +1529,29 @@ impl Completer for ZenohHelper {
     }
 
     fn update(&self, line: &mut LineBuffer, start: usize, elected: &str, cl: &mut Changeset) {
-        assert_eq!(start, 0);
-        line.update(elected, elected.len(), cl);
+        // Commands replace the whole line, and keys the word before the cursor.
+        if start == 0 {
+            line.update(elected, elected.len(), cl);
+        } else {
+            let end = line.pos();
+            line.replace(start..end, elected, cl);
+        }
     }
 }
 
-fn print_key_value((keyexpr, value): KeyValue) {
-    println!(
-        "{}: {}",
-        keyexpr.bright_black(),
-        value.try_to_string().expect("value must be utf-8")
-    );
+fn print_query(query: Query, mode: render::Mode) {
+    let mut line = query.keyexpr.bright_black().to_string();
+    if !query.parameters.is_empty() {
+        line.push_str(&format!("{}{}", "?".bright_black(), query.parameters));
+    }
+    if let Some((payload, encoding)) = query.payload {
+        line.push_str(&format!(": {}", render_payload(&payload, &encoding, mode)));
+    }
+    println!("{line}");
+}
+
+fn print_token_change((keyexpr, kind): TokenChange) {
+    println!("{}: {}", keyexpr.bright_black(), kind_name(kind));
 }
 
 #[derive(Debug, Clone)]
@@ -328,3 +1562,133 @@ impl std::fmt::Display for Exit {
         write!(f, "exit")
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyexpr(line: &str) -> Option<String> {
+        let parsed = Line::parse(line).expect("line must parse");
+        parsed.keyexpr().map(|(_, keyexpr)| keyexpr.to_string())
+    }
+
+    fn hint(line: &str) -> Option<String> {
+        let helper = ZenohHelper {
+            keys: Keys::default(),
+            terminal: true,
+        };
+        helper.hint(line, line.len(), &Context::new(&DefaultHistory::new()))
+    }
+
+    fn highlight(line: &str) -> String {
+        let helper = ZenohHelper {
+            keys: Keys::default(),
+            terminal: true,
+        };
+        helper.highlight(line, line.len()).into_owned()
+    }
+
+    fn complete(keys: &[&str], line: &str) -> (usize, Vec<String>) {
+        let helper = ZenohHelper {
+            keys: Keys::default(),
+            terminal: true,
+        };
+        for key in keys {
+            helper.keys.insert(key);
+        }
+        let (start, candidates) = helper
+            .complete(line, line.len(), &Context::new(&DefaultHistory::new()))
+            .unwrap();
+        let candidates = candidates.into_iter().map(|pair| pair.replacement);
+        (start, candidates.collect())
+    }
+
+    #[test]
+    fn skips_the_values_of_options() {
+        assert_eq!(keyexpr("get --payload \"x ?y\" demo/a").unwrap(), "demo/a");
+        assert_eq!(
+            keyexpr("put --attachment \"a b\" demo/x 1").unwrap(),
+            "demo/x"
+        );
+        assert_eq!(keyexpr("get --timeout=100 demo/a?x").unwrap(), "demo/a");
+        assert_eq!(keyexpr("put -- -demo 1").unwrap(), "-demo");
+    }
+
+    #[test]
+    fn skips_the_values_of_the_options_of_the_command() {
+        assert_eq!(keyexpr("ping -t 500 demo/p").unwrap(), "demo/p");
+        assert_eq!(keyexpr("queryable -t demo/k a=1").unwrap(), "demo/k");
+    }
+
+    #[test]
+    fn validates_the_unquoted_keyexpr() {
+        let invalid = |line| Line::parse(line).unwrap().invalid_keyexpr().is_some();
+        assert!(!invalid("get --payload \"x ?y\" demo/a"));
+        assert!(!invalid("put 'demo/a' 1"));
+        assert!(!invalid("get demo/a?x=#"));
+        assert!(invalid("put demo/a#b 1"));
+        assert!(invalid("put \"demo/a#b\" 1"));
+        assert!(Line::parse("put demo/a \"b").is_err());
+    }
+
+    #[test]
+    fn highlights_the_command_and_an_invalid_keyexpr() {
+        assert_eq!(
+            highlight("put demo/a 1"),
+            format!("{} demo/a 1", "put".bold())
+        );
+        assert_eq!(
+            highlight("get demo/a#b?x"),
+            format!("{} {}?x", "get".bold(), "demo/a#b".red())
+        );
+        assert_eq!(
+            highlight("put \"demo/a#b\" 1"),
+            format!("{} {} 1", "put".bold(), "\"demo/a#b\"".red())
+        );
+        assert_eq!(highlight("unknown demo/a#b"), "unknown demo/a#b");
+        assert_eq!(highlight("put demo/a \"b"), "put demo/a \"b");
+    }
+
+    #[test]
+    fn completes_the_keyexpr() {
+        let keys = ["demo/kitchen/temp", "demo/kid"];
+        assert_eq!(
+            complete(&keys, "get demo/ki"),
+            (4, vec!["demo/kid".to_string(), "demo/kitchen/".to_string()])
+        );
+        assert_eq!(
+            complete(&keys, "put demo/kitchen/temp dem"),
+            (25, Vec::new())
+        );
+        assert_eq!(
+            complete(&keys, "get\u{a0}demo/ki"),
+            (5, vec!["demo/kid".to_string(), "demo/kitchen/".to_string()])
+        );
+        assert_eq!(complete(&keys, "get a\u{a0}b"), (8, Vec::new()));
+    }
+
+    #[test]
+    fn hints_the_missing_arguments() {
+        assert_eq!(hint("pu").unwrap(), "t");
+        assert_eq!(hint("put ").unwrap(), "<keyexpr> <payload> [--encoding ..]");
+        assert_eq!(hint("put demo/a ").unwrap(), "<payload> [--encoding ..]");
+        assert_eq!(hint("put --encoding json ").unwrap(), "<keyexpr> <payload>");
+        assert_eq!(
+            hint("put --attachment \"a b\" demo/a ").unwrap(),
+            "<payload> [--encoding ..]"
+        );
+        assert_eq!(
+            hint("ping -t 500 ").unwrap(),
+            "<keyexpr> [--count ..] [--interval ..]"
+        );
+    }
+
+    #[test]
+    fn hints_nothing_while_an_argument_is_typed() {
+        assert_eq!(hint("put demo/a"), None);
+        assert_eq!(hint("put demo/a \"b "), None);
+        assert_eq!(hint("get --payload "), None);
+        assert_eq!(hint("ping -t "), None);
+        assert_eq!(hint("zid "), None);
+    }
+}
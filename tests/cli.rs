@@ -1,11 +1,15 @@
-use std::io::{BufRead, BufReader};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
 use std::process::Stdio;
 use std::sync::mpsc;
 use std::thread;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use insta_cmd::assert_cmd_snapshot;
-use zenoh::bytes::ZBytes;
+use tempdir::TempDir;
+use zenoh::bytes::{Encoding, ZBytes};
+use zenoh::qos::{CongestionControl, Priority};
+use zenoh::sample::SampleKind;
 
 mod zenoht;
 
@@ -27,6 +31,175 @@ fn test_getting_a_value() {
     assert_cmd_snapshot!(session.cli().args(["get", "test/foo"]));
 }
 
+#[test]
+fn test_getting_a_binary_value() {
+    let session = zenoht::builder()
+        .add_storage(
+            "test",
+            zenoht::Storage::with_bytes(&[(
+                "foo",
+                &[0xca, 0xfe, 0x00, 0x01, b'z', b'e', b'n', b'o', b'h'],
+            )]),
+        )
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["get", "test/foo"]));
+}
+
+#[test]
+fn test_getting_a_value_with_a_render_mode() {
+    let session = zenoht::builder()
+        .add_storage("test", zenoht::Storage::with_entries(&[("foo", "bar")]))
+        .start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["get", "--render", "base64", "test/foo"])
+    );
+}
+
+#[test]
+fn test_getting_values_as_json() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_storage(
+            "test",
+            zenoht::Storage::with_entries(&[("foo", "bar"), ("baz", "qux")]),
+        )
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["--format", "json", "get", "test/foo"]));
+}
+
+#[test]
+fn test_getting_values_as_csv() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_storage(
+            "test",
+            zenoht::Storage::with_entries(&[("foo", "hello, \"world\"")]),
+        )
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["--format", "csv", "get", "test/foo"]));
+}
+
+#[test]
+fn test_getting_a_binary_value_raw() {
+    let session = zenoht::builder()
+        .add_storage(
+            "test",
+            zenoht::Storage::with_bytes(&[("foo", &[0xca, 0xfe, 0x00, 0x01])]),
+        )
+        .start();
+
+    let output = session
+        .cli()
+        .args(["--format", "raw", "get", "test/foo"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+    assert_eq!(output.stdout, [0xca, 0xfe, 0x00, 0x01, b'\n']);
+}
+
+#[test]
+fn test_getting_no_values_as_jsonl() {
+    let session = zenoht::builder()
+        .add_storage("test", zenoht::Storage::empty())
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["--format", "jsonl", "get", "test/foo"]));
+}
+
+#[test]
+fn test_getting_with_query_options() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_echo_queryable("test/echo")
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "get",
+        "--verbose",
+        "--target",
+        "all",
+        "--consolidation",
+        "none",
+        "--timeout",
+        "5000",
+        "--payload",
+        r#"{"temp": 25}"#,
+        "--encoding",
+        "application/json",
+        "--attachment",
+        "meta",
+        "test/echo"
+    ]));
+}
+
+#[test]
+fn test_getting_with_an_invalid_target() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args(["get", "--target", "any", "test/foo"]));
+}
+
+#[test]
+fn test_failing_on_no_replies() {
+    let session = zenoht::builder()
+        .add_storage("test", zenoht::Storage::empty())
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["get", "--fail-on-empty", "test/foo"]));
+}
+
+#[test]
+fn test_getting_an_error_reply() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_queryable("test/foo", &[Err("storage is unavailable")])
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["get", "test/foo"]));
+}
+
+#[test]
+fn test_getting_values_and_error_replies() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_queryable(
+            "test/*",
+            &[
+                Ok(("test/foo", "bar")),
+                Err("test/baz is unavailable"),
+                Ok(("test/qux", "quux")),
+            ],
+        )
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "get",
+        "--verbose",
+        "--consolidation",
+        "none",
+        "test/*"
+    ]));
+}
+
+#[test]
+fn test_getting_values_and_error_replies_as_jsonl() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .add_queryable(
+            "test/*",
+            &[Ok(("test/foo", "bar")), Err("test/baz is unavailable")],
+        )
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["--format", "jsonl", "get", "test/*"]));
+}
+
 #[test]
 fn test_putting_a_value() {
     let storage = zenoht::Storage::empty();
@@ -40,6 +213,162 @@ fn test_putting_a_value() {
     assert_eq!(value, Some(ZBytes::from("bar")));
 }
 
+#[test]
+fn test_putting_a_value_with_options() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    assert_cmd_snapshot!(session.cli().args([
+        "put",
+        "--encoding",
+        "application/json",
+        "--priority",
+        "interactive-high",
+        "--congestion-control",
+        "block",
+        "--express",
+        "--reliability",
+        "best-effort",
+        "--attachment",
+        "source=cli",
+        "--timestamp",
+        "test/foo",
+        r#"{"temp": 25}"#,
+    ]));
+
+    let sample = subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    assert_eq!(sample.key_expr().as_str(), "test/foo");
+    assert_eq!(sample.payload(), &ZBytes::from(r#"{"temp": 25}"#));
+    assert_eq!(sample.encoding(), &Encoding::APPLICATION_JSON);
+    assert_eq!(sample.priority(), Priority::InteractiveHigh);
+    assert_eq!(sample.congestion_control(), CongestionControl::Block);
+    assert!(sample.express());
+    assert_eq!(sample.attachment(), Some(&ZBytes::from("source=cli")));
+    assert!(sample.timestamp().is_some());
+}
+
+#[test]
+fn test_putting_a_value_with_several_attachments() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "put",
+        "--attachment",
+        "a=1",
+        "--attachment",
+        "b=2",
+        "test/foo",
+        "bar"
+    ]));
+}
+
+#[test]
+fn test_putting_a_value_with_an_invalid_priority() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["put", "--priority", "urgent", "test/foo", "bar"])
+    );
+}
+
+#[test]
+fn test_putting_a_value_with_spaces() {
+    let storage = zenoht::Storage::empty();
+    let session = zenoht::builder()
+        .add_storage("test", storage.clone())
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["put", "test/foo", "hello", "world"]));
+
+    let value = session.block_on(async { storage.get("foo").await });
+    assert_eq!(value, Some(ZBytes::from("hello world")));
+}
+
+#[test]
+fn test_putting_a_value_from_a_file() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    let dir = TempDir::new("zenoh-cli").unwrap();
+    let path = dir.path().join("image.png");
+    let bytes = [0x89, b'P', b'N', b'G', 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0xff];
+    fs::write(&path, bytes).unwrap();
+
+    let output = session
+        .cli()
+        .args(["put", "test/foo", &format!("@{}", path.display())])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let sample = subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    assert_eq!(sample.payload().to_bytes(), &bytes[..]);
+    assert_eq!(sample.encoding(), &Encoding::IMAGE_PNG);
+}
+
+#[test]
+fn test_putting_a_value_from_stdin() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["put", "test/foo", "-"])
+            .pass_stdin("hello\nworld\n")
+    );
+
+    let sample = subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    assert_eq!(sample.payload(), &ZBytes::from("hello\nworld\n"));
+    assert_eq!(sample.encoding(), &Encoding::TEXT_PLAIN);
+}
+
+#[test]
+fn test_putting_quoted_values_interactively() {
+    let storage = zenoht::Storage::empty();
+    let session = zenoht::builder()
+        .add_storage("test", storage.clone())
+        .start();
+
+    assert_cmd_snapshot!(session.cli().pass_stdin(
+        r#"put test/foo "hello  world"
+put test/bar '{"temp": 25}'
+put 'test/baz' "it's"
+put test/qux "unterminated
+put test/escaped hello\ world
+put test/apostrophe don\'t
+put test/mixed "a"  b
+quit
+"#
+    ));
+
+    let value = session.block_on(async { storage.get("foo").await });
+    assert_eq!(value, Some(ZBytes::from("hello  world")));
+    let value = session.block_on(async { storage.get("bar").await });
+    assert_eq!(value, Some(ZBytes::from(r#"{"temp": 25}"#)));
+    let value = session.block_on(async { storage.get("baz").await });
+    assert_eq!(value, Some(ZBytes::from("it's")));
+    let value = session.block_on(async { storage.get("qux").await });
+    assert_eq!(value, None);
+    let value = session.block_on(async { storage.get("escaped").await });
+    assert_eq!(value, Some(ZBytes::from("hello world")));
+    let value = session.block_on(async { storage.get("apostrophe").await });
+    assert_eq!(value, Some(ZBytes::from("don't")));
+    let value = session.block_on(async { storage.get("mixed").await });
+    assert_eq!(value, Some(ZBytes::from("a  b")));
+}
+
 #[test]
 fn test_deleting_a_value() {
     let storage = zenoht::Storage::with_entries(&[("foo", "bar")]);
@@ -56,6 +385,222 @@ fn test_deleting_a_value() {
     assert_eq!(value, None);
 }
 
+#[test]
+fn test_deleting_a_value_with_options() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    assert_cmd_snapshot!(session.cli().args([
+        "delete",
+        "--priority",
+        "data-low",
+        "--attachment",
+        "source=cli",
+        "--timestamp",
+        "test/foo",
+    ]));
+
+    let sample = subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    assert_eq!(sample.kind(), SampleKind::Delete);
+    assert_eq!(sample.priority(), Priority::DataLow);
+    assert_eq!(sample.attachment(), Some(&ZBytes::from("source=cli")));
+    assert!(sample.timestamp().is_some());
+}
+
+#[test]
+fn test_publishing_lines_from_stdin() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["pub", "--count", "2", "test/foo"])
+            .pass_stdin("first\nsecond\r\nthird\n")
+    );
+
+    let payloads = (0..2)
+        .map(|_| {
+            subscriber
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .expect("failed to receive the sample")
+        })
+        .map(|sample| sample.payload().try_to_string().unwrap().into_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(payloads, ["first", "second"]);
+}
+
+#[test]
+fn test_publishing_lines_from_a_file_repeatedly() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    let dir = TempDir::new("zenoh-cli").unwrap();
+    let path = dir.path().join("readings.json");
+    fs::write(&path, "{\"temp\": 25}\n{\"temp\": 26}\n").unwrap();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "pub",
+        "--rate",
+        "100",
+        "--repeat",
+        "--count",
+        "3",
+        "test/foo",
+        &format!("@{}", path.display()),
+    ]));
+
+    let samples = (0..3)
+        .map(|_| {
+            subscriber
+                .recv_timeout(Duration::from_secs(5))
+                .unwrap()
+                .expect("failed to receive the sample")
+        })
+        .collect::<Vec<_>>();
+    let payloads = samples
+        .iter()
+        .map(|sample| sample.payload().try_to_string().unwrap().into_owned())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        payloads,
+        [r#"{"temp": 25}"#, r#"{"temp": 26}"#, r#"{"temp": 25}"#]
+    );
+    assert_eq!(samples[0].encoding(), &Encoding::APPLICATION_JSON);
+}
+
+#[test]
+fn test_publishing_a_template_periodically() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    assert_cmd_snapshot!(session.cli().args([
+        "pub",
+        "--interval",
+        "10",
+        "--count",
+        "3",
+        "test/foo",
+        r#"{"seq": {counter}, "time": {timestamp}, "data": "{random:8}"}"#,
+    ]));
+
+    for seq in 0..3 {
+        let sample = subscriber
+            .recv_timeout(Duration::from_secs(5))
+            .unwrap()
+            .expect("failed to receive the sample");
+        let payload: serde_json::Value =
+            serde_json::from_slice(&sample.payload().to_bytes()).unwrap();
+        assert_eq!(payload["seq"], seq);
+        assert!(payload["time"].as_u64().unwrap() > 0);
+        let data = payload["data"].as_str().unwrap();
+        assert_eq!(data.len(), 8);
+        assert!(data.chars().all(|c| c.is_ascii_alphanumeric()));
+    }
+}
+
+#[test]
+fn test_publishing_at_a_too_high_rate() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["pub", "--rate", "1e12", "--count", "2", "test/foo", "bar"])
+    );
+}
+
+#[test]
+fn test_publishing_with_a_zero_interval() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "pub",
+        "--interval",
+        "0",
+        "--count",
+        "2",
+        "test/foo",
+        "bar"
+    ]));
+}
+
+#[test]
+fn test_interrupting_repeated_publishing_interactively() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    let dir = TempDir::new("zenoh-cli").unwrap();
+    let path = dir.path().join("readings.txt");
+    fs::write(&path, "first\nsecond\n").unwrap();
+
+    let mut child = session
+        .cli()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "pub --repeat test/foo @{}", path.display()).unwrap();
+
+    subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    // Ctrl-C is only handled once the CLI waits for it, it would exit before.
+    thread::sleep(Duration::from_millis(200));
+    zenoht::interrupt(&child);
+    if !stops_publishing(&subscriber) {
+        // Left running, it would keep the CPU busy for the other tests.
+        child.kill().unwrap();
+        panic!("still publishing after the interruption");
+    }
+
+    writeln!(stdin, "quit").unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
+#[test]
+fn test_interrupting_a_throughput_benchmark_interactively() {
+    let session = zenoht::builder().start();
+    let subscriber = session.declare_subscriber("test/**");
+
+    let mut child = session
+        .cli()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(
+        stdin,
+        "bench throughput --publish --duration 600 test/bench"
+    )
+    .unwrap();
+
+    subscriber
+        .recv_timeout(Duration::from_secs(5))
+        .unwrap()
+        .expect("failed to receive the sample");
+    // Ctrl-C is only handled once the CLI waits for it, it would exit before.
+    thread::sleep(Duration::from_millis(200));
+    zenoht::interrupt(&child);
+    if !stops_publishing(&subscriber) {
+        // Left running, it would keep the CPU busy for the other tests.
+        child.kill().unwrap();
+        panic!("still publishing after the interruption");
+    }
+
+    writeln!(stdin, "quit").unwrap();
+    assert!(child.wait().unwrap().success());
+}
+
 #[test]
 fn test_subscribing_to_a_keyexpr() {
     let session = zenoht::builder().start();
@@ -97,28 +642,915 @@ fn test_subscribing_to_a_keyexpr() {
 }
 
 #[test]
-fn test_getting_zid() {
-    let session = zenoht::builder()
-        .with_cli_config("id", r#""102030405060708090a0b0c0d0e0f10""#)
-        .start();
+fn test_subscribing_to_a_keyexpr_verbosely() {
+    let session = zenoht::builder().start();
 
-    assert_cmd_snapshot!(session.cli().arg("zid"));
+    let mut child = session
+        .cli()
+        .args(["sub", "-v", "test/**"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    session.wait_for_peer();
+    session.put("test/foo", "bar");
+    session.delete("test/foo");
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let lines = BufReader::new(stdout)
+            .lines()
+            .take(16)
+            .map(Result::unwrap)
+            .collect::<Vec<_>>();
+
+        assert!(lines[0].contains("test/foo"));
+        assert!(lines[0].contains("bar"));
+        assert!(lines[1].contains("kind: put"));
+        assert!(lines[2].contains("encoding: zenoh/bytes"));
+        assert!(lines[4].contains("priority: data"));
+        assert!(lines[5].contains("congestion control: drop"));
+        assert!(lines[6].contains("express: false"));
+        assert!(lines[7].contains("attachment: none"));
+
+        assert!(lines[8].contains("test/foo"));
+        assert!(lines[9].contains("kind: delete"));
+
+        tx.send(()).unwrap();
+    });
+
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("failed to receive sent messages");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
 }
 
 #[test]
-fn test_getting_peers() {
-    let session = zenoht::builder()
-        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
-        .start();
+fn test_subscribing_to_a_high_rate_publisher() {
+    const COUNT: usize = 10_000;
 
-    assert_cmd_snapshot!(session.cli().arg("peers"));
+    let session = zenoht::builder().start();
+
+    let mut child = session
+        .cli()
+        .args(["sub", "test/**"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        for line in BufReader::new(stdout).lines() {
+            if tx.send(line.unwrap()).is_err() {
+                break;
+            }
+        }
+    });
+
+    session.wait_for_peer();
+    loop {
+        session.put("test/ready", "");
+        if let Ok(line) = rx.recv_timeout(Duration::from_millis(100)) {
+            assert!(line.contains("test/ready"));
+            break;
+        }
+    }
+    while rx.recv_timeout(Duration::from_millis(100)).is_ok() {}
+
+    session.publish("test/data", COUNT);
+    for i in 0..COUNT {
+        let line = rx
+            .recv_timeout(Duration::from_secs(10))
+            .expect("failed to receive published messages");
+        assert!(line.contains("test/data"));
+        assert!(line.ends_with(&format!(": {i}")));
+    }
+
+    child.kill().unwrap();
+    child.wait().unwrap();
 }
 
 #[test]
-fn test_getting_routers() {
+fn test_benchmarking_throughput() {
+    let session = zenoht::builder().start();
+
+    let output = session
+        .cli()
+        .args([
+            "--format",
+            "jsonl",
+            "bench",
+            "throughput",
+            "--size",
+            "64",
+            "--duration",
+            "0.5",
+            "test/bench",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let records = output
+        .stdout
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(&line.unwrap()).unwrap())
+        .collect::<Vec<_>>();
+    assert_eq!(records.len(), 2);
+    let (publisher, subscriber) = (&records[0], &records[1]);
+    assert_eq!(publisher["role"], "publisher");
+    assert_eq!(subscriber["role"], "subscriber");
+    assert!(subscriber["samples"].as_u64().unwrap() > 0);
+    assert_eq!(
+        subscriber["bytes"].as_u64().unwrap(),
+        subscriber["samples"].as_u64().unwrap() * 64
+    );
+    assert!(publisher["samples"].as_u64().unwrap() >= subscriber["samples"].as_u64().unwrap());
+    assert_eq!(subscriber["dropped"], 0);
+}
+
+#[test]
+fn test_benchmarking_throughput_with_too_small_payloads() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["bench", "throughput", "--size", "4", "test/bench"])
+    );
+}
+
+#[test]
+fn test_pinging_a_pong() {
+    let session = zenoht::builder().start();
+
+    let mut pong = session
+        .cli()
+        .args(["pong", "test/latency"])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    // Pings are lost until the pong is subscribed.
+    let ready = (0..50).any(|_| {
+        session
+            .cli()
+            .args(["ping", "--count", "1", "--timeout", "100", "test/latency"])
+            .output()
+            .unwrap()
+            .status
+            .success()
+    });
+    assert!(ready, "pong never replied");
+
+    let output = session
+        .cli()
+        .args([
+            "--format",
+            "json",
+            "ping",
+            "--count",
+            "3",
+            "--interval",
+            "10",
+            "--size",
+            "128",
+            "test/latency",
+        ])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let stats: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(stats[0]["transmitted"], 3);
+    assert_eq!(stats[0]["received"], 3);
+    assert_eq!(stats[0]["loss"], 0.0);
+    let min = stats[0]["min_ms"].as_f64().unwrap();
+    let max = stats[0]["max_ms"].as_f64().unwrap();
+    assert!(0.0 < min && min <= max);
+
+    pong.kill().unwrap();
+    pong.wait().unwrap();
+}
+
+#[test]
+fn test_pinging_without_a_pong() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "ping",
+        "--count",
+        "2",
+        "--interval",
+        "10",
+        "--timeout",
+        "100",
+        "test/latency"
+    ]));
+}
+
+#[test]
+fn test_pinging_with_a_zero_interval() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args([
+        "ping",
+        "--interval",
+        "0",
+        "--count",
+        "1",
+        "test/latency"
+    ]));
+}
+
+#[test]
+fn test_pinging_with_too_small_payloads() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["ping", "--size", "4", "--count", "1", "test/ping"])
+    );
+}
+
+#[test]
+fn test_replying_to_queries() {
+    let session = zenoht::builder().start();
+
+    let mut child = session
+        .cli()
+        .args(["queryable", "test/foo", "bar"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    session.wait_for_peer();
+    let replies = session.wait_for_replies("test/foo?answer=42");
+    assert_eq!(replies, vec![("test/foo".to_string(), ZBytes::from("bar"))]);
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut lines = BufReader::new(stdout).lines();
+
+        let line = lines.next().unwrap().unwrap();
+        assert!(line.contains("test/foo"));
+        assert!(line.contains("answer=42"));
+
+        tx.send(()).unwrap();
+    });
+
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("failed to receive queries");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_replying_to_queries_from_a_table() {
+    let session = zenoht::builder().start();
+
+    let mut child = session
+        .cli()
+        .args(["queryable", "--table", "test/**", "test/a=1", "test/b=2"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    session.wait_for_peer();
+    let replies = session.wait_for_replies("test/*");
+    assert_eq!(
+        replies,
+        vec![
+            ("test/a".to_string(), ZBytes::from("1")),
+            ("test/b".to_string(), ZBytes::from("2")),
+        ]
+    );
+
+    let replies = session.wait_for_replies("test/b");
+    assert_eq!(replies, vec![("test/b".to_string(), ZBytes::from("2"))]);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_declaring_a_liveliness_token() {
+    let session = zenoht::builder().start();
+
+    let mut child = session
+        .cli()
+        .args(["liveliness", "declare", "test/foo"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    session.wait_for_peer();
+    let tokens = session.wait_for_liveliness("test/**");
+    assert_eq!(tokens, vec!["test/foo".to_string()]);
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_getting_liveliness_tokens() {
+    let session = zenoht::builder().start();
+    let _foo = session.declare_liveliness_token("test/foo");
+    let _bar = session.declare_liveliness_token("test/bar");
+
+    assert_cmd_snapshot!(session.cli().args(["liveliness", "get", "test/foo"]));
+}
+
+#[test]
+fn test_subscribing_to_liveliness_tokens() {
+    let session = zenoht::builder().start();
+    let _foo = session.declare_liveliness_token("test/foo");
+
+    let mut child = session
+        .cli()
+        .args(["liveliness", "sub", "--history", "test/**"])
+        .stderr(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    let stdout = child.stdout.take().unwrap();
+    let (tx, rx) = mpsc::channel();
+
+    thread::spawn(move || {
+        let mut lines = BufReader::new(stdout).lines();
+
+        let line = lines.next().unwrap().unwrap();
+        assert!(line.contains("test/foo"));
+        assert!(line.contains("put"));
+
+        tx.send(()).unwrap();
+
+        let line = lines.next().unwrap().unwrap();
+        assert!(line.contains("test/foo"));
+        assert!(line.contains("delete"));
+
+        tx.send(()).unwrap();
+    });
+
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("failed to receive alive token");
+    drop(_foo);
+    rx.recv_timeout(Duration::from_secs(10))
+        .expect("failed to receive dropped token");
+
+    child.kill().unwrap();
+    child.wait().unwrap();
+}
+
+#[test]
+fn test_getting_zid() {
+    let session = zenoht::builder()
+        .with_cli_config("id", r#""102030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    assert_cmd_snapshot!(session.cli().arg("zid"));
+}
+
+#[test]
+fn test_overriding_config_values() {
+    let session = zenoht::builder()
+        .with_cli_config("id", r#""102030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    assert_cmd_snapshot!(
+        session
+            .cli()
+            .args(["--cfg", r#"id:"a0b0c0d0e0f10""#, "zid"])
+    );
+}
+
+#[test]
+fn test_connecting_without_a_config_file() {
     let session = zenoht::builder()
         .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
         .start();
 
-    assert_cmd_snapshot!(session.cli().arg("routers"));
+    assert_cmd_snapshot!(session.cli().env_remove("ZENOH_CONFIG").args([
+        "--mode",
+        "client",
+        "--connect",
+        session.router_endpoint(),
+        "--no-multicast-scouting",
+        "--format",
+        "raw",
+        "routers"
+    ]));
+}
+
+#[test]
+fn test_getting_peers() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    assert_cmd_snapshot!(session.cli().arg("peers"));
+}
+
+#[test]
+fn test_getting_routers_as_jsonl() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    let output = session
+        .cli()
+        .args(["--format", "jsonl", "routers"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The age depends on how long the command takes, and the locators need the admin space.
+    let router: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(router["zid"], "202030405060708090a0b0c0d0e0f10");
+    assert_eq!(router["whatami"], "router");
+    assert_eq!(router["protocols"], serde_json::json!(["tcp"]));
+    assert_eq!(router["locators"], serde_json::json!([]));
+    assert!(router["age_secs"].as_f64().unwrap() >= 0.0);
+}
+
+#[test]
+fn test_getting_routers() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .start();
+
+    let output = session.cli().arg("routers").output().unwrap();
+    assert!(output.status.success());
+
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines[0].split_whitespace().collect::<Vec<_>>(),
+        ["ZID", "WHATAMI", "PROTOCOLS", "LOCATORS", "AGE"]
+    );
+    let [zid, whatami, protocols, locators, age] =
+        lines[1].split_whitespace().collect::<Vec<_>>()[..]
+    else {
+        panic!("unexpected row: {}", lines[1]);
+    };
+    assert_eq!(zid, "202030405060708090a0b0c0d0e0f10");
+    assert_eq!(whatami, "router");
+    assert_eq!(protocols, "tcp");
+    assert_eq!(locators, session.router_endpoint());
+    assert!(age.ends_with('s'));
+    assert_eq!(lines.len(), 2);
+}
+
+#[test]
+fn test_listing_nodes() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .with_cli_config("id", r#""303030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    // A client that is only reachable through the router.
+    let mut client = session
+        .cli()
+        .args([
+            "--mode",
+            "client",
+            "--cfg",
+            r#"id:"505030405060708090a0b0c0d0e0f10""#,
+            "pong",
+            "test/client",
+        ])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let nodes = (0..50)
+        .find_map(|_| {
+            let output = session
+                .cli()
+                .args(["--format", "json", "nodes"])
+                .output()
+                .unwrap();
+            assert!(output.status.success());
+            let nodes: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+            if nodes.as_array().unwrap().len() == 3 {
+                return Some(nodes);
+            }
+            thread::sleep(Duration::from_millis(100));
+            None
+        })
+        .expect("client never connected");
+
+    let fields = |node: &serde_json::Value| {
+        (
+            node["zid"].as_str().unwrap().to_string(),
+            node["whatami"].as_str().unwrap().to_string(),
+            node["connection"].as_str().unwrap().to_string(),
+        )
+    };
+    let nodes = nodes.as_array().unwrap();
+    assert_eq!(
+        nodes.iter().map(fields).collect::<Vec<_>>(),
+        [
+            (
+                "303030405060708090a0b0c0d0e0f10".to_string(),
+                "peer".to_string(),
+                "self".to_string()
+            ),
+            (
+                "202030405060708090a0b0c0d0e0f10".to_string(),
+                "router".to_string(),
+                "direct".to_string()
+            ),
+            (
+                "505030405060708090a0b0c0d0e0f10".to_string(),
+                "client".to_string(),
+                "via 202030405060708090a0b0c0d0e0f10".to_string()
+            ),
+        ]
+    );
+    assert_eq!(
+        nodes[1]["locators"],
+        serde_json::json!([session.router_endpoint()])
+    );
+    assert_eq!(nodes[2]["protocols"], serde_json::json!(["tcp"]));
+    assert_eq!(nodes[2]["age_secs"], serde_json::Value::Null);
+
+    client.kill().unwrap();
+    client.wait().unwrap();
+}
+
+/// A router replying to scouting on loopback, with an address of its own to not find the routers
+/// of the other tests.
+fn scouted_router() -> zenoht::Session {
+    let port = port_check::free_local_port().unwrap();
+    let address = format!(r#""224.0.0.224:{port}""#);
+    zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("scouting/multicast/enabled", "true")
+        .with_router_config("scouting/multicast/interface", r#""lo""#)
+        .with_router_config("scouting/multicast/address", &address)
+        .with_cli_config("scouting/multicast/interface", r#""lo""#)
+        .with_cli_config("scouting/multicast/address", &address)
+        .start()
+}
+
+#[test]
+fn test_scouting_for_routers() {
+    let session = scouted_router();
+
+    let output = session
+        .cli()
+        .args(["--format", "json", "scout", "--what", "router"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let hellos: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        hellos,
+        serde_json::json!([{
+            "zid": "202030405060708090a0b0c0d0e0f10",
+            "whatami": "router",
+            "locators": [session.router_endpoint()],
+        }])
+    );
+}
+
+#[test]
+fn test_scouting_without_connecting() {
+    let session = scouted_router();
+
+    // The session would fail to open, as nothing listens on the endpoint.
+    assert_cmd_snapshot!(session.cli().args([
+        "--mode",
+        "client",
+        "--connect",
+        "tcp/127.0.0.1:1",
+        "--format",
+        "raw",
+        "scout",
+        "--timeout",
+        "500",
+    ]));
+}
+
+#[test]
+fn test_scouting_for_an_unknown_node_kind() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args(["scout", "--what", "router,broker"]));
+}
+
+#[test]
+fn test_listing_routers_from_the_admin_space() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .start();
+
+    let output = session
+        .cli()
+        .args(["--format", "json", "admin", "routers"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    // The version and the locators depend on the build and on the port of the router.
+    let routers: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let [router] = routers.as_array().unwrap().as_slice() else {
+        panic!("expected a single router: {routers}");
+    };
+    assert_eq!(router["zid"], "202030405060708090a0b0c0d0e0f10");
+    assert!(router["version"].as_str().unwrap().starts_with('v'));
+    assert_eq!(
+        router["locators"],
+        serde_json::json!([session.router_endpoint()])
+    );
+    assert_eq!(router["sessions"], 1);
+}
+
+#[test]
+fn test_listing_sessions_from_the_admin_space() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .with_cli_config("id", r#""303030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    let output = session
+        .cli()
+        .args(["--format", "json", "admin", "sessions"])
+        .output()
+        .unwrap();
+    assert!(output.status.success());
+
+    let sessions: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    let [cli] = sessions.as_array().unwrap().as_slice() else {
+        panic!("expected a single session: {sessions}");
+    };
+    assert_eq!(cli["router"], "202030405060708090a0b0c0d0e0f10");
+    assert_eq!(cli["peer"], "303030405060708090a0b0c0d0e0f10");
+    assert_eq!(cli["whatami"], "peer");
+    let links = cli["links"].as_array().unwrap();
+    assert_eq!(links.len(), 1);
+    assert!(
+        links[0]
+            .as_str()
+            .unwrap()
+            .starts_with(&format!("{} -> ", session.router_endpoint()))
+    );
+}
+
+#[test]
+fn test_listing_plugins_from_the_admin_space() {
+    let session = zenoht::builder()
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .start();
+
+    assert_cmd_snapshot!(session.cli().args(["admin", "plugins"]));
+}
+
+#[test]
+fn test_listing_the_linkstate_from_the_admin_space() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .start();
+
+    // A second router to have a link between them.
+    let port = port_check::free_local_port().unwrap();
+    let mut router = session
+        .cli()
+        .args([
+            "--mode",
+            "router",
+            "--listen",
+            &format!("tcp/127.0.0.1:{port}"),
+            "--connect",
+            session.router_endpoint(),
+            "--cfg",
+            r#"id:"404030405060708090a0b0c0d0e0f10""#,
+            "--cfg",
+            "adminspace/enabled:true",
+            "pong",
+            "test/router",
+        ])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let linked = (0..50).any(|_| {
+        let output = session
+            .cli()
+            .args(["--format", "raw", "admin", "linkstate"])
+            .output()
+            .unwrap();
+        if output.stdout.lines().count() == 2 {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+        false
+    });
+    assert!(linked, "routers never linked");
+
+    assert_cmd_snapshot!(session.cli().args(["admin", "linkstate"]));
+
+    router.kill().unwrap();
+    router.wait().unwrap();
+}
+
+#[test]
+fn test_exporting_the_topology() {
+    let session = zenoht::builder()
+        .with_router_config("id", r#""202030405060708090a0b0c0d0e0f10""#)
+        .with_router_config("adminspace", r#"{"enabled": true}"#)
+        .with_cli_config("id", r#""303030405060708090a0b0c0d0e0f10""#)
+        .start();
+
+    // A second router, only reachable through the first one.
+    let port = port_check::free_local_port().unwrap();
+    let mut router = session
+        .cli()
+        .args([
+            "--mode",
+            "router",
+            "--listen",
+            &format!("tcp/127.0.0.1:{port}"),
+            "--connect",
+            session.router_endpoint(),
+            "--cfg",
+            r#"id:"404030405060708090a0b0c0d0e0f10""#,
+            "--cfg",
+            "adminspace/enabled:true",
+            "pong",
+            "test/router",
+        ])
+        .stdout(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let linked = (0..50).any(|_| {
+        let output = session
+            .cli()
+            .args(["--format", "raw", "admin", "linkstate"])
+            .output()
+            .unwrap();
+        if output.stdout.lines().count() == 2 {
+            return true;
+        }
+        thread::sleep(Duration::from_millis(100));
+        false
+    });
+    assert!(linked, "routers never linked");
+
+    assert_cmd_snapshot!(session.cli().args(["topology"]));
+    assert_cmd_snapshot!(session.cli().args(["topology", "mermaid"]));
+
+    let output = session.cli().args(["topology", "json"]).output().unwrap();
+    assert!(output.status.success());
+    let topology: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(
+        topology["links"][1],
+        serde_json::json!({
+            "from": "202030405060708090a0b0c0d0e0f10",
+            "to": "404030405060708090a0b0c0d0e0f10",
+            "weight": 100.5,
+        })
+    );
+    assert_eq!(
+        topology["nodes"][2]["locators"],
+        serde_json::json!([format!("tcp/127.0.0.1:{port}")])
+    );
+
+    router.kill().unwrap();
+    router.wait().unwrap();
+}
+
+#[test]
+fn test_exporting_the_topology_in_an_unknown_format() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args(["topology", "svg"]));
+}
+
+#[test]
+fn test_querying_a_disabled_admin_space() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args(["admin", "routers"]));
+}
+
+#[test]
+fn test_running_an_unknown_admin_command() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().args(["admin", "foo"]));
+}
+
+#[test]
+fn test_listing_the_history() {
+    let session = zenoht::builder().start();
+    let dir = session.data_dir().join("zenoh-cli");
+    fs::create_dir_all(&dir).unwrap();
+    fs::write(
+        dir.join("history"),
+        "#V2\nput demo/a 1\nget demo/**\nput demo/b 2\nzid\n",
+    )
+    .unwrap();
+
+    assert_cmd_snapshot!(session.cli().args(["history"]));
+    assert_cmd_snapshot!(session.cli().args(["history", "-n", "1", "put"]));
+    assert_cmd_snapshot!(session.cli().args(["--no-history", "history"]));
+}
+
+#[test]
+fn test_starting_with_a_history_that_fails_to_load() {
+    let session = zenoht::builder().start();
+    // A directory can't be read as the history file.
+    fs::create_dir_all(session.data_dir().join("zenoh-cli/history")).unwrap();
+
+    let mut child = session
+        .cli()
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+    let mut stdin = child.stdin.take().unwrap();
+    writeln!(stdin, "put test/foo bar\nquit").unwrap();
+    let output = child.wait_with_output().unwrap();
+
+    assert!(output.status.success());
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    assert!(stdout.contains("error: failed to load history from"));
+    assert!(stdout.ends_with("ok\n"));
+}
+
+#[test]
+fn test_failing_to_connect() {
+    let session = zenoht::builder().start();
+
+    let output = session
+        .cli()
+        .env_remove("ZENOH_CONFIG")
+        .args([
+            "--mode",
+            "client",
+            "--connect",
+            "tcp/127.0.0.1:1",
+            "--no-multicast-scouting",
+            "zid",
+        ])
+        .output()
+        .expect("failed to run the cli");
+
+    // The error mentions where it's raised in zenoh, so only its start is checked.
+    assert_eq!(output.status.code(), Some(2));
+    assert!(
+        String::from_utf8_lossy(&output.stderr)
+            .starts_with("error: failed to create zenoh session: Unable to connect")
+    );
+}
+
+#[test]
+fn test_running_an_unknown_command() {
+    let session = zenoht::builder().start();
+
+    assert_cmd_snapshot!(session.cli().arg("foo"));
+}
+
+/// Waits for the samples already sent to arrive, then makes sure no more are.
+fn stops_publishing(
+    subscriber: &zenoh::pubsub::Subscriber<
+        zenoh::handlers::FifoChannelHandler<zenoh::sample::Sample>,
+    >,
+) -> bool {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while subscriber
+        .recv_timeout(Duration::from_millis(500))
+        .unwrap()
+        .is_some()
+    {
+        if Instant::now() >= deadline {
+            return false;
+        }
+    }
+    true
 }
@@ -1,7 +1,7 @@
 use std::collections::HashMap;
 use std::fs::File;
-use std::path::PathBuf;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command};
 use std::sync::Arc;
 use std::time::Duration;
 
@@ -10,14 +10,19 @@ use port_check::free_local_port;
 use tempdir::TempDir;
 use tokio::sync::Mutex;
 use zenoh::bytes::ZBytes;
+use zenoh::handlers::FifoChannelHandler;
 
 pub type KeyExpr = String;
 
+/// A reply of a queryable, a key and a value or an error message.
+type QueryReply = Result<(KeyExpr, String), String>;
+
 pub struct Session {
     runtime: tokio::runtime::Runtime,
     session: Arc<zenoh::Session>,
     config_path: PathBuf,
-    _temp_dir: TempDir,
+    router_endpoint: String,
+    temp_dir: TempDir,
 }
 
 impl Drop for Session {
@@ -31,10 +36,21 @@ impl Drop for Session {
 impl Session {
     pub fn cli(&self) -> Command {
         let mut cmd = Command::new(get_cargo_bin("zenoh-cli"));
-        cmd.env("ZENOH_CONFIG", self.config_path.to_owned());
+        cmd.env("ZENOH_CONFIG", &self.config_path);
+        // Keeps the history of interactive mode out of the home directory.
+        cmd.env("XDG_DATA_HOME", self.temp_dir.path());
         cmd
     }
 
+    /// Where the CLI keeps its data, e.g. the history of interactive mode.
+    pub fn data_dir(&self) -> &Path {
+        self.temp_dir.path()
+    }
+
+    pub fn router_endpoint(&self) -> &str {
+        &self.router_endpoint
+    }
+
     pub fn wait_for_peer(&self) {
         let _guard = self.runtime.enter();
         let fut = tokio::time::timeout(Duration::from_secs(5), async {
@@ -53,6 +69,100 @@ impl Session {
         self.block_on(fut).expect("failed to wait for peer");
     }
 
+    pub fn delete(&self, key: &str) {
+        let key = key.to_string();
+        self.block_on(async move { self.session.delete(key).await })
+            .expect("failed to delete key");
+    }
+
+    pub fn publish(&self, key: &str, count: usize) {
+        let key = key.to_string();
+        self.block_on(async move {
+            let publisher = self
+                .session
+                .declare_publisher(key)
+                .congestion_control(zenoh::qos::CongestionControl::Block)
+                .await?;
+            for i in 0..count {
+                publisher.put(i.to_string()).await?;
+            }
+            zenoh::Result::Ok(())
+        })
+        .expect("failed to publish");
+    }
+
+    pub fn wait_for_replies(&self, selector: &str) -> Vec<(KeyExpr, ZBytes)> {
+        let _guard = self.runtime.enter();
+        let fut = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let replies = self
+                    .session
+                    .get(selector)
+                    .await
+                    .expect("failed to query selector");
+
+                let mut samples = Vec::new();
+                while let Ok(reply) = replies.recv_async().await {
+                    if let Ok(sample) = reply.into_result() {
+                        samples.push((sample.key_expr().to_string(), sample.payload().to_owned()));
+                    }
+                }
+                if !samples.is_empty() {
+                    samples.sort_by(|(a, _), (b, _)| a.cmp(b));
+                    break samples;
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        });
+
+        self.block_on(fut).expect("failed to wait for replies")
+    }
+
+    pub fn wait_for_liveliness(&self, keyexpr: &str) -> Vec<KeyExpr> {
+        let _guard = self.runtime.enter();
+        let fut = tokio::time::timeout(Duration::from_secs(5), async {
+            loop {
+                let replies = self
+                    .session
+                    .liveliness()
+                    .get(keyexpr)
+                    .await
+                    .expect("failed to query liveliness");
+
+                let mut tokens = Vec::new();
+                while let Ok(reply) = replies.recv_async().await {
+                    if let Ok(sample) = reply.into_result() {
+                        tokens.push(sample.key_expr().to_string());
+                    }
+                }
+                if !tokens.is_empty() {
+                    tokens.sort();
+                    break tokens;
+                }
+
+                tokio::time::sleep(Duration::from_millis(100)).await;
+            }
+        });
+
+        self.block_on(fut).expect("failed to wait for liveliness")
+    }
+
+    pub fn declare_liveliness_token(&self, keyexpr: &str) -> zenoh::liveliness::LivelinessToken {
+        let keyexpr = keyexpr.to_string();
+        self.block_on(async move { self.session.liveliness().declare_token(keyexpr).await })
+            .expect("failed to declare liveliness token")
+    }
+
+    pub fn declare_subscriber(
+        &self,
+        keyexpr: &str,
+    ) -> zenoh::pubsub::Subscriber<FifoChannelHandler<zenoh::sample::Sample>> {
+        let keyexpr = keyexpr.to_string();
+        self.block_on(async move { self.session.declare_subscriber(keyexpr).await })
+            .expect("failed to declare subscriber")
+    }
+
     pub fn block_on<F: Future>(&self, future: F) -> F::Output {
         self.runtime.block_on(future)
     }
@@ -66,9 +176,12 @@ impl Session {
 }
 
 pub struct Builder {
+    router_endpoint: String,
     router_config: zenoh::Config,
     cli_config: zenoh::Config,
     storage: HashMap<KeyExpr, Storage>,
+    queryables: HashMap<KeyExpr, Vec<QueryReply>>,
+    echoes: Vec<KeyExpr>,
 }
 
 impl Builder {
@@ -79,6 +192,14 @@ impl Builder {
         router_config
             .insert_json5("mode", r#""router""#)
             .expect("failed to set router mode");
+        // A fixed ID keeps the repliers in snapshots stable, so the routers of the tests running
+        // in parallel must not find each other.
+        router_config
+            .insert_json5("id", r#""202030405060708090a0b0c0d0e0f10""#)
+            .expect("failed to set router id");
+        router_config
+            .insert_json5("scouting/multicast/enabled", "false")
+            .expect("failed to set router scouting config");
         router_config
             .insert_json5(
                 "listen",
@@ -120,9 +241,12 @@ impl Builder {
             .expect("failed to set scouting config");
 
         Builder {
+            router_endpoint: format!("tcp/127.0.0.1:{port}"),
             router_config,
             cli_config,
             storage: HashMap::new(),
+            queryables: HashMap::new(),
+            echoes: Vec::new(),
         }
     }
 
@@ -137,6 +261,30 @@ impl Builder {
         self
     }
 
+    /// Replies to queries with the given replies in order, `Err`s are sent as error replies.
+    pub fn add_queryable(
+        mut self,
+        keyexpr: impl Into<String>,
+        replies: &[Result<(&str, &str), &str>],
+    ) -> Self {
+        let replies = replies
+            .iter()
+            .map(|reply| {
+                reply
+                    .map(|(key, value)| (key.to_string(), value.to_string()))
+                    .map_err(|message| message.to_string())
+            })
+            .collect();
+        self.queryables.insert(keyexpr.into(), replies);
+        self
+    }
+
+    /// Replies to queries with their own payload, encoding and attachment.
+    pub fn add_echo_queryable(mut self, keyexpr: impl Into<String>) -> Self {
+        self.echoes.push(keyexpr.into());
+        self
+    }
+
     pub fn with_cli_config(mut self, key: &str, value: &str) -> Self {
         self.cli_config
             .insert_json5(key, value)
@@ -172,6 +320,39 @@ impl Builder {
                 });
             }
 
+            for (keyexpr, replies) in self.queryables {
+                let queryable = session.declare_queryable(keyexpr).await.unwrap();
+                runtime.spawn(async move {
+                    while let Ok(query) = queryable.recv_async().await {
+                        for reply in &replies {
+                            match reply {
+                                Ok((key, value)) => query.reply(key, value).await.unwrap(),
+                                Err(message) => query.reply_err(message).await.unwrap(),
+                            }
+                        }
+                    }
+                });
+            }
+
+            for keyexpr in self.echoes {
+                let queryable = session.declare_queryable(keyexpr).await.unwrap();
+                runtime.spawn(async move {
+                    while let Ok(query) = queryable.recv_async().await {
+                        let mut reply = query.reply(
+                            query.key_expr(),
+                            query.payload().cloned().unwrap_or_default(),
+                        );
+                        if let Some(encoding) = query.encoding() {
+                            reply = reply.encoding(encoding.clone());
+                        }
+                        if let Some(attachment) = query.attachment() {
+                            reply = reply.attachment(attachment.clone());
+                        }
+                        reply.await.unwrap();
+                    }
+                });
+            }
+
             session
         });
 
@@ -179,11 +360,21 @@ impl Builder {
             runtime,
             session,
             config_path,
-            _temp_dir: temp_dir,
+            router_endpoint: self.router_endpoint,
+            temp_dir,
         }
     }
 }
 
+/// Sends SIGINT to the CLI, like pressing Ctrl-C in its terminal.
+pub fn interrupt(child: &Child) {
+    let status = Command::new("kill")
+        .args(["-INT", &child.id().to_string()])
+        .status()
+        .expect("failed to run kill");
+    assert!(status.success(), "failed to interrupt the CLI");
+}
+
 pub fn builder() -> Builder {
     Builder::new()
 }
@@ -205,6 +396,15 @@ impl Storage {
         )))
     }
 
+    pub fn with_bytes(entries: &[(&str, &[u8])]) -> Self {
+        Storage(Arc::new(Mutex::new(
+            entries
+                .iter()
+                .map(|(k, v)| (k.to_string(), ZBytes::from(v.to_vec())))
+                .collect(),
+        )))
+    }
+
     pub async fn get(&self, key: &str) -> Option<ZBytes> {
         self.0.lock().await.get(key).cloned()
     }